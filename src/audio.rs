@@ -23,6 +23,7 @@ pub struct AudioManager {
    _device: Device,
    sinks: Vec<SinkWrapper>,
    sources: Vec<AudioSource>,
+   playback_rate: f64,
 }
 
 impl AudioManager {
@@ -30,7 +31,7 @@ impl AudioManager {
       let _device = rodio::default_output_device().unwrap();
       let sinks = vec![];
       let sources = vec![];
-      let mut audio_manager = AudioManager { _device, sinks, sources };
+      let mut audio_manager = AudioManager { _device, sinks, sources, playback_rate: 1.0 };
       audio_manager.new_sink();
       audio_manager
    }
@@ -102,7 +103,8 @@ impl AudioManager {
    }
 
    pub fn play_source(&mut self, audio_source_id: usize) {
-      let audio_source = self.get_audio_source(audio_source_id);
+      let mut audio_source = self.get_audio_source(audio_source_id);
+      audio_source.set_playback_rate(self.playback_rate);
       for sink in self.sinks.iter_mut() {
          if sink.empty() {
             sink.append(audio_source);
@@ -117,6 +119,15 @@ impl AudioManager {
       self.sources.get(audio_source_id).unwrap().clone()
    }
 
+   /// Applies to every source played from now on
+   pub fn set_playback_rate(&mut self, playback_rate: f64) {
+      self.playback_rate = playback_rate;
+   }
+
+   pub fn playback_rate(&self) -> f64 {
+      self.playback_rate
+   }
+
    pub fn track_volume(&self, id: usize) -> f32 {
       self.sources.get(id).unwrap().track_volume()
    }
@@ -180,12 +191,18 @@ impl AudioManager {
 
 pub struct AudioSource {
    audiofile: Arc<AudioFile>,
-   current_pos: usize,
+   position: f64, // in frames of the audio file
+   channel: u16,
+   playback_rate: f64,
 }
 
 impl AudioSource {
    pub fn new(filename: &str) -> AudioSource {
-      AudioSource { audiofile: Arc::new(AudioFile::new(filename)), current_pos: 0 }
+      AudioSource::from_file(AudioFile::new(filename))
+   }
+
+   fn from_file(audiofile: AudioFile) -> AudioSource {
+      AudioSource { audiofile: Arc::new(audiofile), position: 0.0, channel: 0, playback_rate: 1.0 }
    }
 
    pub fn track_volume(&self) -> f32 {
@@ -209,17 +226,53 @@ impl AudioSource {
    }
 
    pub fn current_pos(&self) -> usize {
-      self.current_pos
+      self.position as usize * self.channels() as usize + self.channel as usize
    }
 
    pub fn set_current_pos(&mut self, value: usize) {
-      self.current_pos = value
+      self.position = (value / self.channels() as usize) as f64;
+      self.channel = (value % self.channels() as usize) as u16;
+   }
+
+   /// The song plays `playback_rate` times as fast, pitch included
+   pub fn set_playback_rate(&mut self, playback_rate: f64) {
+      self.playback_rate = playback_rate;
+   }
+
+   pub fn playback_rate(&self) -> f64 {
+      self.playback_rate
+   }
+
+   /// Samples left to output at the current playback rate
+   pub fn remaining_samples(&self) -> usize {
+      let channels = self.channels() as usize;
+      let frames = (self.len() / channels) as f64;
+      if self.position >= frames {
+         return 0;
+      }
+      let remaining_frames = ((frames - self.position) / self.playback_rate).ceil() as usize;
+      remaining_frames * channels - self.channel as usize
+   }
+
+   /// Linear interpolation between the two frames around the current position
+   fn resampled_sample(&self) -> Option<i16> {
+      let channels = self.channels() as usize;
+      let frame = self.position.floor();
+      let index = frame as usize * channels + self.channel as usize;
+      let current = self.sample_at(index)? as f64;
+      let next = self.sample_at(index + channels).map_or(current, |sample| sample as f64);
+      Some((current + (next - current) * (self.position - frame)).round() as i16)
    }
 }
 
 impl Clone for AudioSource {
    fn clone(&self) -> AudioSource {
-      AudioSource { audiofile: Arc::clone(&self.audiofile), current_pos: 0 }
+      AudioSource {
+         audiofile: Arc::clone(&self.audiofile),
+         position: 0.0,
+         channel: 0,
+         playback_rate: self.playback_rate,
+      }
    }
 }
 
@@ -227,9 +280,13 @@ impl Iterator for AudioSource {
    type Item = i16;
 
    fn next(&mut self) -> Option<i16> {
-      let result = self.sample_at(self.current_pos());
-      if let Some(_) = result {
-         self.set_current_pos(self.current_pos() + 1);
+      let result = self.resampled_sample();
+      if result.is_some() {
+         self.channel += 1;
+         if self.channel == self.channels() {
+            self.channel = 0;
+            self.position += self.playback_rate;
+         }
       }
       result
    }
@@ -237,7 +294,7 @@ impl Iterator for AudioSource {
 
 impl Source for AudioSource {
    fn current_frame_len(&self) -> Option<usize> {
-      Some(self.len() - self.current_pos())
+      Some(self.remaining_samples())
    }
 
    fn channels(&self) -> u16 {
@@ -249,7 +306,7 @@ impl Source for AudioSource {
    }
 
    fn total_duration(&self) -> Option<Duration> {
-      let sample_per_channel = ((self.len() - self.current_pos()) / self.channels() as usize) as u128;
+      let sample_per_channel = (self.remaining_samples() / self.channels() as usize) as u128;
       let duration_ns = sample_per_channel * 1_000_000_000 / self.sample_rate() as u128;
       let duration_s = duration_ns / 1_000_000_000;
       let duration_ns = duration_ns - (duration_s * 1_000_000_000);
@@ -495,4 +552,29 @@ mod tests {
       }
       test_vol("10,000,000 random i16", &data);
    }
+
+   #[test]
+   fn test_playback_rate() {
+      let file = AudioFile {
+         samples: vec![0, 0, 100, -100, 200, -200, 300, -300],
+         volume: 1.0,
+         channels: 2,
+         sample_rate: 1000,
+      };
+      let mut source = AudioSource::from_file(file);
+      assert_eq!(8, source.remaining_samples());
+
+      source.set_playback_rate(1.5);
+      assert_eq!(6, source.remaining_samples());
+      let samples: Vec<i16> = source.clone().collect();
+      assert_eq!(vec![0, 0, 150, -150, 300, -300], samples);
+      assert_eq!(Some(Duration::from_millis(3)), Source::total_duration(&source.clone()));
+
+      source.set_playback_rate(0.75);
+      source.next();
+      assert_eq!(11, source.remaining_samples());
+      let samples: Vec<i16> = source.collect();
+      assert_eq!(11, samples.len());
+      assert_eq!(vec![0, 75, -75, 150, -150], samples[..5].to_vec());
+   }
 }
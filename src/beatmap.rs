@@ -1,5 +1,10 @@
 use crate::*;
+use game::combo::*;
+use game::gamemod::*;
+use game::health::*;
+use game::score::*;
 
+pub mod difficulty;
 pub mod event;
 pub mod hitobject;
 pub mod settings;
@@ -7,68 +12,175 @@ pub mod timing;
 
 use event::*;
 use global::pixel::*;
+use global::playfield::*;
+use hitobject::visibility::*;
 use hitobject::*;
 use input::*;
 use settings::*;
 use timing::*;
 
 use std::time::{Duration, SystemTime};
-use std::{any, clone, cmp, collections::HashMap, fmt, fs, slice};
+use std::{any, clone, cmp, collections::HashMap, error, fmt, fs, io, slice, str};
 
 use sdl2::render::{Texture, WindowCanvas};
 
+pub const LATEST_FORMAT_VERSION: u32 = 14;
+// maps older than v5 were timed against a different audio offset
+pub const LEGACY_TIMING_OFFSET_VERSION: u32 = 5;
+pub const LEGACY_TIMING_OFFSET: Duration = Duration::from_millis(24);
+pub const LEGACY_TICK_DISTANCE_VERSION: u32 = 8;
+
+// 120 BPM, for sliders in maps without any uninherited timing point
+pub const DEFAULT_BEAT_LENGTH: f64 = 500.0;
+pub const MIN_SLIDER_VELOCITY: f64 = 0.1;
+pub const MAX_SLIDER_VELOCITY: f64 = 10.0;
+
+// osu!pixels between objects for them to stack
+pub const STACK_DISTANCE: f32 = 3.0;
+
+pub const BREAK_FADE_DURATION: Duration = Duration::from_millis(500);
+pub const BREAK_LETTERBOX_HEIGHT: f32 = 0.125;
+pub const BREAK_PROGRESS_BAR_WIDTH: f32 = 0.5;
+pub const BREAK_PROGRESS_BAR_HEIGHT: f32 = 8.0;
+pub const BREAK_PROGRESS_BAR_OFFSET: f32 = 0.1;
+
+pub const DEFAULT_COMBO_COLOURS: [Colour<u8>; 4] = [
+   Colour { r: 255, g: 192, b: 0, a: 255 },
+   Colour { r: 0, g: 202, b: 0, a: 255 },
+   Colour { r: 18, g: 124, b: 255, a: 255 },
+   Colour { r: 242, g: 24, b: 57, a: 255 },
+];
+
 pub struct Beatmap {
    pub settings: BeatmapSettings,
+   /// `settings` with the active mods applied, valid after `prepare`
+   pub effective_settings: BeatmapSettings,
+   pub mods: OsruGameModsActive,
+   format_version: u32,
 
    pub timing_points: Vec<TimingPoint>,
    pub hitobjects: Vec<HitObject>,
    pub event_backgrounds: Vec<EventBackground>,
+   pub event_breaks: Vec<EventBreak>,
+   pub beatmap_colours: BeatmapColour,
    /*
    pub event_backgrounds : Vec<EventBackground>,
    pub event_videos: Vec<EventVideo>,
-   pub event_breaks: Vec<EventBreak>,
    // storyboard
-   pub beatmap_colours: Vec<BeatmapColour>,
    */
+   /// Problems that didn't stop the beatmap from loading, such as unknown keys
+   pub warnings: Vec<BeatmapError>,
    update_start_index: usize,
    draw_start_index: usize,
    draw_end_index: usize,
    animation_timings: AnimationTiming,
+   visibility: VisibilityPolicy,
+   /// Every judgement made so far, in order
+   pub judgements: Vec<Judgement>,
+   judgements_processed: usize,
+   failed: bool,
+   health: Health,
+   score: Score,
+   combo: Combo,
+   pub playfield: Playfield,
+   // transforms currently applied to the hit objects' positions
+   playfield_transforms: Vec<PlayfieldTransform>,
 }
 impl Beatmap {
    fn new() -> Beatmap {
       Beatmap {
          settings: BeatmapSettings::new(),
+         effective_settings: BeatmapSettings::new(),
+         mods: OsruGameModsActive::new(),
+         format_version: LATEST_FORMAT_VERSION,
 
          timing_points: vec![],
          hitobjects: vec![],
+         playfield_transforms: vec![],
          event_backgrounds: vec![],
+         event_breaks: vec![],
+         beatmap_colours: BeatmapColour::default(),
          /*
          event_videos: vec![],
-         event_breaks: vec![],
-         beatmap_colours: vec![],
          //hitobjects
          */
+         warnings: vec![],
          update_start_index: 0,
          draw_start_index: 0,
          draw_end_index: 0,
          animation_timings: AnimationTiming::default(),
+         visibility: VisibilityPolicy::default(),
+         judgements: vec![],
+         judgements_processed: 0,
+         failed: false,
+         health: Health::default(),
+         score: Score::default(),
+         combo: Combo::default(),
+         playfield: Playfield::new(&PixRect::new(
+            Pix::screen_pix(0.0),
+            Pix::screen_pix(0.0),
+            DEFAULT_WINDOW_SIZE.x(),
+            DEFAULT_WINDOW_SIZE.y(),
+         )),
+      }
+   }
+
+   pub fn load(filename: &str) -> Result<Beatmap, BeatmapError> {
+      match fs::read_to_string(filename) {
+         Ok(file) => Beatmap::parse(filename, &file),
+         Err(error) => Err(BeatmapError {
+            filename: nstr(filename),
+            section: BeatmapSection::General,
+            line_number: 0,
+            line: nstr(""),
+            kind: BeatmapErrorKind::Io(error),
+         }),
       }
    }
 
-   pub fn load(filename: &str) -> Beatmap {
+   pub fn parse(filename: &str, file: &str) -> Result<Beatmap, BeatmapError> {
       let mut beatmap = Beatmap::new();
-      let file = fs::read_to_string(filename).unwrap();
-      let mut last_hitobj_pos_x = -50;
-      let mut last_hitobj_pos_y = -50;
 
       use BeatmapSection::*;
       let mut section = General;
-      'next_line: for line in file.lines() {
-         let line = line.trim();
+      let mut combo_colours = vec![];
+      'next_line: for (line_index, raw_line) in file.lines().enumerate() {
+         let line_number = line_index + 1;
+         let diagnostic = move |kind| BeatmapError {
+            filename: nstr(filename),
+            section,
+            line_number,
+            line: nstr(raw_line),
+            kind,
+         };
+         // reports a field that can't be parsed and skips the rest of the line
+         macro_rules! field_or_skip {
+            ($result:expr) => {
+               match $result {
+                  Ok(value) => value,
+                  Err(kind) => {
+                     beatmap.warnings.push(diagnostic(kind));
+                     continue 'next_line;
+                  }
+               }
+            };
+         }
+
+         let line = raw_line.trim();
          if line.starts_with("//") || line.len() == 0 {
             continue 'next_line;
          }
+         if line_number == 1 {
+            let header = line.trim_start_matches('\u{feff}');
+            if let Some(version) = header.strip_prefix("osu file format v") {
+               match version.trim().parse() {
+                  Ok(version) => beatmap.format_version = version,
+                  Err(_) => beatmap.warnings.push(diagnostic(BeatmapErrorKind::InvalidValue)),
+               }
+               continue 'next_line;
+            }
+            beatmap.warnings.push(diagnostic(BeatmapErrorKind::MissingHeader));
+         }
          if line.contains("[") && line.contains("]") && line.len() > 2 {
             let line = line.trim_start_matches("[");
             let line = line.trim_end_matches("]");
@@ -81,19 +193,27 @@ impl Beatmap {
             }
          } else if section == General || section == Editor || section == Metadata || section == Difficulty {
             if let Some((k, value)) = parse_key_value(line, ":") {
-               let mut key = BeatmapSettingName::from_str(k);
+               let key = match BeatmapSettingName::from_str(k) {
+                  Some(key) => key,
+                  None => {
+                     beatmap.warnings.push(diagnostic(BeatmapErrorKind::UnknownKey));
+                     continue 'next_line;
+                  }
+               };
 
                if let Some(old_value) = beatmap.settings.get(&key) {
-                  if section == Metadata && key == BeatmapSettingName::Tags {
-                     if let Some(result) = OsruType::parse_type(value, old_value, Some(" ")) {
-                        beatmap.settings.set(&key, result);
-                     }
-                  } else if let Some(result) = OsruType::parse_type(value, old_value, None) {
+                  let separator =
+                     if section == Metadata && key == BeatmapSettingName::Tags { Some(" ") } else { None };
+                  if let Some(result) = OsruType::parse_type(value, old_value, separator) {
                      beatmap.settings.set(&key, result);
+                  } else if !value.is_empty() {
+                     beatmap.warnings.push(diagnostic(BeatmapErrorKind::InvalidValue));
                   }
+               } else {
+                  beatmap.warnings.push(diagnostic(BeatmapErrorKind::UnknownKey));
                }
             } else {
-               continue 'next_line;
+               beatmap.warnings.push(diagnostic(BeatmapErrorKind::MalformedLine));
             }
          } else if section == Events {
             let line = parse_list(line, ",");
@@ -115,145 +235,379 @@ impl Beatmap {
                      filename,
                      offset_from_center,
                   });
+               } else if line[0] == "2" || line[0] == "Break" {
+                  let start_time = parse_field::<u64>(&line, 1);
+                  let end_time = parse_field::<u64>(&line, 2);
+                  if let (Ok(start_time), Ok(end_time)) = (start_time, end_time) {
+                     beatmap.event_breaks.push(EventBreak {
+                        start_time: Duration::from_millis(start_time) + beatmap.timing_offset(),
+                        end_time: Duration::from_millis(end_time) + beatmap.timing_offset(),
+                     });
+                  } else {
+                     beatmap.warnings.push(diagnostic(BeatmapErrorKind::InvalidValue));
+                  }
                }
                // TODO: OTHER EVENTS
             }
          } else if section == TimingPoints {
             let line = parse_list(line, ",");
-            if line.len() >= 8 {
-               let t = TimingPoint {
-                  start_time: line[0].trim().parse().unwrap_or_default(),
-                  beat_length: line[1].trim().parse().unwrap_or_default(),
-                  meter: line[2].trim().parse().unwrap_or_default(),
-                  sample_set: line[3].trim().parse().unwrap_or_default(),
-                  sample_index: line[4].trim().parse().unwrap_or_default(),
-                  volume: line[5].trim().parse().unwrap_or_default(),
-                  uninherited: line[6].trim().parse().unwrap_or_default(),
-                  effects: line[7].trim().parse().unwrap_or_default(),
+            let t = TimingPoint {
+               start_time: field_or_skip!(parse_field::<f64>(&line, 0)).round() as isize,
+               beat_length: field_or_skip!(parse_field(&line, 1)),
+               meter: parse_field(&line, 2).unwrap_or(4),
+               sample_set: parse_field(&line, 3).unwrap_or_default(),
+               sample_index: parse_field(&line, 4).unwrap_or_default(),
+               volume: parse_field(&line, 5).unwrap_or(100),
+               uninherited: parse_field(&line, 6).unwrap_or(1),
+               effects: parse_field(&line, 7).unwrap_or_default(),
+            };
+            beatmap.timing_points.push(t);
+         } else if section == Colours {
+            if let Some((key, value)) = parse_key_value(line, ":") {
+               let colour = match parse_colour(value) {
+                  Some(colour) => colour,
+                  None => {
+                     beatmap.warnings.push(diagnostic(BeatmapErrorKind::InvalidValue));
+                     continue 'next_line;
+                  }
                };
-               beatmap.timing_points.push(t);
+               if let Some(Ok(combo_number)) = key.strip_prefix("Combo").map(str::parse::<u32>) {
+                  combo_colours.push((combo_number, colour));
+               } else if key == "SliderTrackOverride" {
+                  beatmap.beatmap_colours.slider_track_override_colour = Some(colour);
+               } else if key == "SliderBorder" {
+                  beatmap.beatmap_colours.slider_border_colour = Some(colour);
+               } else {
+                  beatmap.warnings.push(diagnostic(BeatmapErrorKind::UnknownKey));
+               }
+            } else {
+               beatmap.warnings.push(diagnostic(BeatmapErrorKind::MalformedLine));
             }
-         } else if section == Colours {
-            ///////////////////////////////////////////////////////////////////////////
          } else if section == HitObjects {
             let line = parse_list(line, ",");
-            if line.len() >= 4 {
-               let position = {
-                  let mut x: i32 = line[0].trim().parse().unwrap_or_default();
-                  let mut y: i32 = line[1].trim().parse().unwrap_or_default();
-                  if ((x - last_hitobj_pos_x).pow(2) + (y - last_hitobj_pos_y).pow(2)) < 25 {
-                     x += 5;
-                     y += 5;
-                  }
-                  last_hitobj_pos_x = x;
-                  last_hitobj_pos_y = y;
-                  Pix2D::new(Pix::osru_pix(x as f32), Pix::osru_pix(y as f32))
+            let position = {
+               let x = field_or_skip!(parse_field(&line, 0));
+               let y = field_or_skip!(parse_field(&line, 1));
+               Pix2D::new(Pix::osru_pix(x), Pix::osru_pix(y))
+            };
+            let time = field_or_skip!(parse_time(&line, 2)) + beatmap.timing_offset();
+            let type_bitflags: u32 = field_or_skip!(parse_field(&line, 3));
+            let hitsound_bitflags: u32 = parse_field(&line, 4).unwrap_or_default();
+            let hitsounds = OsruHitSounds::from_bitflags(Bitflags(hitsound_bitflags));
+            let new_combo = type_bitflags & 0b100 == 0b100;
+            let combo_colours_to_skip = (type_bitflags & 0b1110000) >> 4;
+
+            if type_bitflags & 0b1 == 0b1 {
+               //hitcircle
+               let hitsample = parse_hitsample(&line, 5).unwrap_or_else(|kind| {
+                  beatmap.warnings.push(diagnostic(kind));
+                  HitSample::default()
+               });
+               let hitcircle = hitcircle::HitCircle {
+                  position,
+                  time,
+                  new_combo,
+                  combo_colours_to_skip,
+                  hitsounds,
+                  hitsample_set: hitsample.normal_set,
+                  hitsample_additional_set: hitsample.addition_set,
+                  hitsample_index: hitsample.index,
+                  hitsample_volume: hitsample.volume,
+                  hitsample_filename: hitsample.filename,
+                  ..Default::default()
                };
-               let time = Duration::from_millis(line[2].trim().parse::<u64>().unwrap_or_default())
-                  + BEATMAP_TIMING_OFFSET;
-               let type_bitflags = line[3].trim().parse::<u32>().unwrap_or_default();
-               let hitsound_bitflags = line[4].trim().parse::<u32>().unwrap_or_default();
-               let hitsounds = OsruHitSounds::from_bitflags(Bitflags(hitsound_bitflags));
-               let new_combo = type_bitflags & 0b100 == 0b100;
-               let combo_colours_to_skip = (type_bitflags & 0b1110000) >> 4;
-
-               if type_bitflags & 0b1 == 0b1 {
-                  //hitcircle
-                  let hitcircle = hitcircle::HitCircle {
-                     position,
-                     time,
-                     new_combo,
-                     combo_colours_to_skip,
-                     hitsounds,
-                     ..Default::default()
+               beatmap.hitobjects.push(HitObject::HitCircle(hitcircle));
+            } else if type_bitflags & 0b10 == 0b10 {
+               //slider
+               use slider::SliderCurveType::{self, *};
+               let curve: String = field_or_skip!(parse_field(&line, 5));
+               let curve = parse_list(&curve, "|");
+               let mut iter = curve.iter();
+               let mut curve_type = SliderCurveType::default();
+               let mut curve_points = vec![position];
+               if let Some(c) = iter.next() {
+                  curve_type = match *c {
+                     "C" => CentripetalCatmullRom,
+                     "L" => Linear,
+                     "P" => PerfectCircle,
+                     _ => Bezier,
                   };
-                  beatmap.hitobjects.push(HitObject::HitCircle(hitcircle));
-               } else if type_bitflags & 0b10 == 0b10 {
-                  //slider
-                  use slider::SliderCurveType::{self, *};
-                  let curve = line[5].trim().parse::<String>().unwrap_or_default();
-                  let curve = curve.trim();
-                  let curve = parse_list(curve, "|");
-                  let mut iter = curve.iter();
-                  let mut curve_type = SliderCurveType::default();
-                  let mut curve_points = vec![position];
-                  if let Some(c) = iter.next() {
-                     curve_type = match *c {
-                        "C" => CentripetalCatmullRom,
-                        "L" => Linear,
-                        "P" => PerfectCircle,
-                        _ => Bezier,
-                     };
-
-                     for pt in iter {
-                        let split = parse_list(pt, ":");
-                        if split.len() == 2 {
-                           let x = split[0].trim().parse().unwrap_or_default();
-                           let y = split[1].trim().parse().unwrap_or_default();
-                           let curve_point = Pix2D::new(Pix::osru_pix(x), Pix::osru_pix(y));
-                           curve_points.push(curve_point);
-                        }
-                     }
-                     curve_points.shrink_to_fit();
+
+                  for pt in iter {
+                     let split = parse_list(pt, ":");
+                     let x = field_or_skip!(parse_field(&split, 0));
+                     let y = field_or_skip!(parse_field(&split, 1));
+                     let curve_point = Pix2D::new(Pix::osru_pix(x), Pix::osru_pix(y));
+                     curve_points.push(curve_point);
                   }
-                  let num_slides = line[6].trim().parse::<u32>().unwrap_or(1);
-                  let length_of_slider =
-                     Pix::OsruPix(line[7].trim().parse::<f32>().unwrap_or_default() / 10.0);
-                  let mut edge_sounds = vec![];
-                  if line.len() >= 9 {
-                     let line8 = parse_list(line[8], "|");
-                     for sound in line8 {
-                        edge_sounds.push(sound.trim().parse::<i32>().unwrap_or(0));
-                     }
+                  curve_points.shrink_to_fit();
+               }
+               let num_slides = field_or_skip!(parse_field::<u32>(&line, 6));
+               let length_of_slider = Pix::OsruPix(field_or_skip!(parse_field::<f32>(&line, 7)));
+               let mut edge_sounds = vec![];
+               if let Ok(field) = parse_field::<String>(&line, 8) {
+                  for sound in parse_list(&field, "|") {
+                     let flags = sound.trim().parse::<u32>().unwrap_or_default();
+                     edge_sounds.push(OsruHitSounds::from_bitflags(Bitflags(flags)));
                   }
-                  //let mut edge_sets = vec![];
-                  if line.len() >= 10 {
-                     let line9 = parse_list(line[9], "|");
-                     for sound in line9 {
-                        //edge_sounds.push(sound.trim().parse::<isize>().unwrap_or(0));
-                        // TODO: deal with hitsound bitflag
-                     }
+               }
+               let mut edge_sets = vec![];
+               if let Ok(field) = parse_field::<String>(&line, 9) {
+                  for set in parse_list(&field, "|") {
+                     let set = parse_list(set, ":");
+                     edge_sets.push(slider::EdgeSet {
+                        normal_set: parse_field(&set, 0).unwrap_or_default(),
+                        addition_set: parse_field(&set, 1).unwrap_or_default(),
+                     });
                   }
+               }
+               let hitsample = parse_hitsample(&line, 10).unwrap_or_else(|kind| {
+                  beatmap.warnings.push(diagnostic(kind));
+                  HitSample::default()
+               });
 
-                  let slider = slider::Slider {
-                     curve_points,
-                     time,
-                     new_combo,
-                     combo_colours_to_skip,
-                     curve_type,
-                     num_slides,
-                     length_of_slider,
-                     ..Default::default()
-                  };
-               //beatmap.hitobjects.push(HitObject::Slider(slider));
-
-               //println!("slider {:?}", line);
-               } else if type_bitflags & 0b1000 == 0b1000 {
-                  // TODO: spiner
-                  //println!("spinner {:?}", line);
-               } else if (type_bitflags & 0b1000_0000) == 0b1000_0000 {
-                  // LOW PRIORITY: mania hold
-                  println!("mania {:?}", line);
+               let path = slider::path::SliderPath::new(curve_type, &curve_points, length_of_slider);
+               let beat_length = beatmap.beat_length_at(time);
+               let slider_velocity = beatmap.slider_velocity_at(time);
+               // tick spacing ignored slider velocity before v8
+               let tick_distance_multiplier = if beatmap.format_version < LEGACY_TICK_DISTANCE_VERSION {
+                  1.0 / slider_velocity
                } else {
-                  println!("unknown {:?}", line);
-               }
+                  1.0
+               };
+               let slider = slider::Slider {
+                  curve_points,
+                  path,
+                  beat_length,
+                  slider_velocity,
+                  tick_distance_multiplier,
+                  time,
+                  new_combo,
+                  combo_colours_to_skip,
+                  curve_type,
+                  num_slides,
+                  length_of_slider,
+                  hitsounds,
+                  edge_sounds,
+                  edge_sets,
+                  hitsample_set: hitsample.normal_set,
+                  hitsample_additional_set: hitsample.addition_set,
+                  hitsample_index: hitsample.index,
+                  hitsample_volume: hitsample.volume,
+                  hitsample_filename: hitsample.filename,
+                  ..Default::default()
+               };
+               beatmap.hitobjects.push(HitObject::Slider(slider));
+            } else if type_bitflags & 0b1000 == 0b1000 {
+               let end_time = field_or_skip!(parse_time(&line, 5)) + beatmap.timing_offset();
+               let spinner = spinner::Spinner {
+                  time,
+                  end_time,
+                  new_combo,
+                  combo_colours_to_skip,
+                  hitsounds,
+                  ..Default::default()
+               };
+               beatmap.hitobjects.push(HitObject::Spinner(spinner));
+            } else if (type_bitflags & 0b1000_0000) == 0b1000_0000 {
+               // LOW PRIORITY: mania hold
+               println!("mania {:?}", line);
+            } else {
+               beatmap.warnings.push(diagnostic(BeatmapErrorKind::InvalidValue));
             }
          }
       }
+      combo_colours.sort_by_key(|(combo_number, _)| *combo_number);
+      beatmap.beatmap_colours.combo_colours = combo_colours.into_iter().map(|(_, colour)| colour).collect();
+
       //println!("settings {:?}", beatmap.settings);
       //println!("timing points {:?}", beatmap.timing_points);
       beatmap.hitobjects.shrink_to_fit();
       beatmap.event_backgrounds.shrink_to_fit();
+      beatmap.event_breaks.shrink_to_fit();
       beatmap.timing_points.shrink_to_fit();
+      beatmap.settings.apply_defaults(beatmap.format_version);
       beatmap.settings.shrink_to_fit();
-      beatmap
+      Ok(beatmap)
+   }
+
+   pub fn format_version(&self) -> u32 {
+      self.format_version
+   }
+
+   pub fn timing_offset(&self) -> Duration {
+      if self.format_version < LEGACY_TIMING_OFFSET_VERSION {
+         BEATMAP_TIMING_OFFSET + LEGACY_TIMING_OFFSET
+      } else {
+         BEATMAP_TIMING_OFFSET
+      }
+   }
+
+   /// Uninherited timing point active at `time`, or the first one for objects placed before it
+   pub fn uninherited_timing_point_at(&self, time: Duration) -> Option<&TimingPoint> {
+      let time = self.timing_point_time(time);
+      let uninherited = self.timing_points.iter().filter(|timing_point| timing_point.is_uninherited());
+      let first = uninherited.clone().next();
+      uninherited.take_while(|timing_point| timing_point.start_time <= time).last().or(first)
+   }
+
+   /// Beat length in milliseconds at `time`
+   pub fn beat_length_at(&self, time: Duration) -> f64 {
+      self.uninherited_timing_point_at(time).map_or(DEFAULT_BEAT_LENGTH, TimingPoint::beat_length)
+   }
+
+   /// Slider velocity multiplier at `time`. Each uninherited timing point resets it to 1.0.
+   pub fn slider_velocity_at(&self, time: Duration) -> f64 {
+      let time = self.timing_point_time(time);
+      self
+         .timing_points
+         .iter()
+         .take_while(|timing_point| timing_point.start_time <= time)
+         .last()
+         .map_or(1.0, TimingPoint::slider_velocity)
    }
 
-   pub fn prepare(&mut self, viewport_size: &PixRect) {
-      self.animation_timings =
-         AnimationTiming::new_from(self.settings.overall_difficulty(), self.settings.approach_rate());
+   /// Converts a time on the game timeline to the milliseconds used by timing points
+   fn timing_point_time(&self, time: Duration) -> isize {
+      time.saturating_sub(self.timing_offset()).as_millis() as isize
+   }
+
+   pub fn prepare(&mut self, viewport_size: &PixRect, mods: &OsruGameModsActive) {
+      self.mods = mods.clone();
+      self.effective_settings = self.settings.with_mods(mods);
+      self.animation_timings = AnimationTiming::new_from(
+         self.effective_settings.overall_difficulty(),
+         self.effective_settings.approach_rate(),
+      );
+      self.visibility = VisibilityPolicy::new(mods);
+      self.health = Health::new(self.effective_settings.hp_drain_rate());
+      self.transform_positions(mods.playfield_transforms());
+      for hitobj in self.hitobjects.iter_mut() {
+         hitobj.prepare_timing(&self.effective_settings);
+      }
+      self.apply_stacking();
+      self.playfield = Playfield::new(viewport_size);
       for hitobj in self.hitobjects.iter_mut() {
-         hitobj.prepare(viewport_size, &self.settings);
+         hitobj.prepare(&self.playfield, &self.effective_settings);
+         if let HitObject::Slider(slider) = hitobj {
+            slider.track_colour_override = self.beatmap_colours.slider_track_override_colour;
+            slider.border_colour = self.beatmap_colours.slider_border_colour;
+         }
+      }
+      self.assign_combos();
+
+      let perfect_play: Vec<Judgement> =
+         self.hitobjects.iter().flat_map(|hitobj| hitobj.perfect_judgements()).collect();
+      let difficulty_multiplier =
+         difficulty_multiplier(&self.settings, self.hitobjects.len(), self.drain_time());
+      self.score = Score::new(ScoringMode::new(mods), mods, difficulty_multiplier, &perfect_play);
+      self.combo = Combo::new(&perfect_play);
+   }
+
+   /// Moves every object by `transforms`, undoing the ones from a previous `prepare` first
+   fn transform_positions(&mut self, transforms: Vec<PlayfieldTransform>) {
+      let inverses = self.playfield_transforms.iter().rev().map(PlayfieldTransform::inverse);
+      for transform in inverses.chain(transforms.iter().copied()) {
+         for hitobj in self.hitobjects.iter_mut() {
+            hitobj.apply_transform(&transform);
+         }
+      }
+      self.playfield_transforms = transforms;
+   }
+
+   /// Gives overlapping objects close in time a stack height, walking backwards so the latest object stays
+   /// on top. A slider's end stacks with the objects following it.
+   fn apply_stacking(&mut self) {
+      let stack_threshold =
+         self.animation_timings.preempt_duration().mul_f64(self.settings.stack_leniency().max(0.0));
+      let is_stacked = |a: Pix2D, b: Pix2D| {
+         let diff = a - b;
+         diff.x().get().hypot(diff.y().get()) < STACK_DISTANCE
+      };
+      for hitobj in self.hitobjects.iter_mut() {
+         hitobj.set_stack_height(0);
+      }
+
+      for i in (0..self.hitobjects.len()).rev() {
+         let mut current = i;
+         let object = &self.hitobjects[current];
+         if object.stack_height() != 0 || object.is_spinner() {
+            continue;
+         }
+
+         if let HitObject::HitCircle(_) = object {
+            for n in (0..i).rev() {
+               let (previous, object) = (&self.hitobjects[n], &self.hitobjects[current]);
+               if previous.is_spinner() {
+                  continue;
+               }
+               if object.time().saturating_sub(previous.end_time()) > stack_threshold {
+                  break;
+               }
+               if let HitObject::Slider(_) = previous {
+                  if is_stacked(previous.end_position(), object.position()) {
+                     // objects stacked on a slider's end move away from it instead
+                     let offset = object.stack_height() - previous.stack_height() + 1;
+                     let end_position = previous.end_position();
+                     for j in n + 1..=i {
+                        let stacked = &mut self.hitobjects[j];
+                        if is_stacked(end_position, stacked.position()) {
+                           stacked.set_stack_height(stacked.stack_height() - offset);
+                        }
+                     }
+                     break;
+                  }
+               }
+               if is_stacked(previous.position(), object.position()) {
+                  let stack_height = object.stack_height() + 1;
+                  self.hitobjects[n].set_stack_height(stack_height);
+                  current = n;
+               }
+            }
+         } else {
+            for n in (0..i).rev() {
+               let (previous, object) = (&self.hitobjects[n], &self.hitobjects[current]);
+               if previous.is_spinner() {
+                  continue;
+               }
+               if object.time().saturating_sub(previous.time()) > stack_threshold {
+                  break;
+               }
+               if is_stacked(previous.end_position(), object.position()) {
+                  let stack_height = object.stack_height() + 1;
+                  self.hitobjects[n].set_stack_height(stack_height);
+                  current = n;
+               }
+            }
+         }
+      }
+   }
+
+   /// Numbers objects within their combo and gives them its colour. Spinners don't take a number, and the
+   /// object after one always starts a new combo.
+   fn assign_combos(&mut self) {
+      let combo_colours = self.beatmap_colours.combo_colours();
+      let mut colour_index = 0;
+      let mut combo_number = 0;
+      let mut after_spinner = false;
+      for hitobj in self.hitobjects.iter_mut() {
+         if hitobj.is_spinner() {
+            hitobj.set_combo_number(0);
+            after_spinner = true;
+            continue;
+         }
+         let first = combo_number == 0;
+         if first || hitobj.new_combo() || after_spinner {
+            if !first {
+               colour_index += 1;
+            }
+            colour_index += hitobj.combo_colours_to_skip() as usize;
+            combo_number = 0;
+         }
+         combo_number += 1;
+         after_spinner = false;
+         hitobj.set_combo_colour(combo_colours[colour_index % combo_colours.len()]);
+         hitobj.set_combo_number(combo_number);
       }
    }
 
@@ -262,14 +616,16 @@ impl Beatmap {
 
       input_manager.poll_all();
 
-      'nextObj: while self.update_start_index < self.hitobjects.len() {
+      'nextObj: while self.update_start_index < self.hitobjects.len() && !self.failed {
          input_manager.poll_one();
          let hitobj = self.hitobjects.get_mut(self.update_start_index).unwrap();
          if hitobj.hit_state().is_ready() || hitobj.hit_state().not_yet_drawing() {
             if let Some(update) = input_manager.next_update() {
-               if hitobj.update(&update, &self.animation_timings) == InputConsumed {
+               let result = hitobj.update(&update, &self.animation_timings, &mut self.judgements);
+               if result == InputConsumed {
                   self.update_start_index += 1;
                }
+               self.process_judgements();
             } else {
                break 'nextObj;
             }
@@ -282,6 +638,9 @@ impl Beatmap {
    pub fn full_update(&mut self, input_manager: &mut InputManager) {
       input_manager.force_time_update();
       self.lazy_update(input_manager);
+      if self.failed {
+         return;
+      }
 
       let update = InputUpdate::new(input_manager.curr_snapshot(), input_manager.curr_snapshot());
 
@@ -290,7 +649,7 @@ impl Beatmap {
 
       for i in draw_start_index..self.hitobjects.len() {
          let hitobj = self.hitobjects.get_mut(i).unwrap();
-         hitobj.update(&update, &self.animation_timings);
+         hitobj.update(&update, &self.animation_timings, &mut self.judgements);
          if !hitobj.hit_state().is_done() {
             if i < self.draw_start_index {
                self.draw_start_index = i;
@@ -303,6 +662,30 @@ impl Beatmap {
             }
          }
       }
+      let time = *input_manager.curr_snapshot().time();
+      self.health.update(time, self.is_draining(time));
+      self.process_judgements();
+   }
+
+   /// Time from the first object to the end of the last one, not counting breaks
+   pub fn drain_time(&self) -> Duration {
+      match (self.hitobjects.first(), self.hitobjects.last()) {
+         (Some(first), Some(last)) => {
+            let breaks = self.event_breaks.iter().map(EventBreak::duration).sum();
+            last.end_time().saturating_sub(first.time()).saturating_sub(breaks)
+         }
+         _ => Duration::from_secs(0),
+      }
+   }
+
+   /// Health drains from the first object to the end of the last one, except during breaks
+   fn is_draining(&self, time: Duration) -> bool {
+      match (self.hitobjects.first(), self.hitobjects.last()) {
+         (Some(first), Some(last)) => {
+            time >= first.time() && time <= last.end_time() && self.break_at(time).is_none()
+         }
+         _ => false,
+      }
    }
 
    pub fn draw(
@@ -313,10 +696,18 @@ impl Beatmap {
 
       for i in self.draw_start_index..self.draw_end_index {
          input_manager.poll_one();
-         self.hitobjects.get(i).unwrap().draw_self(canvas, texture_manager);
+         self.hitobjects.get(i).unwrap().draw_self(
+            canvas,
+            texture_manager,
+            &self.animation_timings,
+            &self.visibility,
+         );
       }
       for i in self.draw_start_index..self.draw_end_index {
          input_manager.poll_one();
+         if !self.visibility.shows_approach_circle(i == 0) {
+            continue;
+         }
          self.hitobjects.get(i).unwrap().draw_approach_circle(
             canvas,
             texture_manager,
@@ -324,11 +715,190 @@ impl Beatmap {
             *input_manager.curr_snapshot().time(),
          );
       }
+      self.draw_break_overlay(canvas, texture_manager, *input_manager.curr_snapshot().time());
+   }
+
+   pub fn break_at(&self, time: Duration) -> Option<&EventBreak> {
+      self.event_breaks.iter().find(|event_break| event_break.contains(time))
+   }
+
+   /// How far into the current break we are, from 0.0 outside of breaks to 1.0 once it has fully faded in
+   pub fn break_fade(&self, time: Duration) -> f32 {
+      if let Some(event_break) = self.break_at(time) {
+         let since_start = time - event_break.start_time;
+         let fade = cmp::min(since_start, event_break.remaining(time)).as_secs_f32()
+            / BREAK_FADE_DURATION.as_secs_f32();
+         fade.min(1.0)
+      } else {
+         0.0
+      }
+   }
+
+   /// Whether a slider is currently being followed with a key held
+   pub fn is_slider_held(&self) -> bool {
+      self.hitobjects[self.draw_start_index.min(self.hitobjects.len())..].iter().any(|hitobj| match hitobj {
+         HitObject::Slider(slider) => slider.hit_state == HitState::Tracking && slider.tracking,
+         _ => false,
+      })
+   }
+
+   pub fn background_dim(&self, time: Duration) -> u8 {
+      let fade = self.break_fade(time);
+      (BACKGROUND_DIM as f32 - (BACKGROUND_DIM - BREAK_BACKGROUND_DIM) as f32 * fade).round() as u8
+   }
+
+   fn draw_break_overlay(
+      &self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager, time: Duration,
+   ) {
+      let event_break = match self.break_at(time) {
+         Some(event_break) => event_break,
+         None => return,
+      };
+      let fade = self.break_fade(time);
+      let viewport = PixRect::new_from_sdl2_rect(canvas.viewport());
+
+      if self.settings.letterbox_in_breaks() {
+         let bar_height = (viewport.height() * BREAK_LETTERBOX_HEIGHT * fade).get_round() as u32;
+         if bar_height > 0 {
+            let width = viewport.width().get_round() as u32;
+            let bottom = viewport.height().get_round() - bar_height as i32;
+            canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, u8::MAX));
+            canvas.fill_rect(sdl2::rect::Rect::new(0, 0, width, bar_height)).unwrap();
+            canvas.fill_rect(sdl2::rect::Rect::new(0, bottom, width, bar_height)).unwrap();
+         }
+      }
+
+      let alpha = (u8::MAX as f32 * fade).round() as u8;
+      let centre = Pix2D::new(viewport.width() / 2, viewport.height() / 2);
+      let remaining = event_break.remaining(time);
+      draw_number(
+         canvas,
+         texture_manager,
+         remaining.as_secs_f32().ceil() as u64,
+         TextureName::default_digit,
+         &centre,
+         ScalingFactor(1.0),
+         Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: alpha },
+      );
+
+      let progress = remaining.as_secs_f32() / event_break.duration().as_secs_f32();
+      let bar_width = viewport.width() * BREAK_PROGRESS_BAR_WIDTH * progress;
+      let bar = PixRect::new(
+         centre.x() - bar_width / 2,
+         centre.y() + viewport.height() * BREAK_PROGRESS_BAR_OFFSET,
+         bar_width,
+         Pix::screen_pix(BREAK_PROGRESS_BAR_HEIGHT),
+      );
+      canvas.set_draw_color(sdl2::pixels::Color::RGBA(u8::MAX, u8::MAX, u8::MAX, alpha / 2));
+      canvas.fill_rect(bar.to_sdl2_rect()).unwrap();
+   }
+
+   /// Applies every judgement made since the last call to health, score and combo and runs the fail
+   /// conditions
+   fn process_judgements(&mut self) {
+      while let Some(judgement) = self.judgements.get(self.judgements_processed) {
+         self.judgements_processed += 1;
+         self.health.judge(judgement);
+         self.score.judge(judgement, &self.combo);
+         self.combo.judge(judgement);
+         if self.mods.fails_on(judgement) {
+            self.failed = true;
+         }
+      }
+      if self.health.is_empty() && !self.mods.is_enabled(OsruGameModName::NoFail) {
+         self.failed = true;
+      }
+   }
+
+   pub fn health(&self) -> &Health {
+      &self.health
+   }
+
+   pub fn score(&self) -> &Score {
+      &self.score
+   }
+
+   pub fn combo(&self) -> &Combo {
+      &self.combo
    }
 
+   /// Whether the play was a full or perfect combo, once the beatmap is done
+   pub fn combo_result(&self) -> Option<ComboResult> {
+      if self.is_done() {
+         Some(self.combo.result())
+      } else {
+         None
+      }
+   }
+
+   pub fn has_failed(&self) -> bool {
+      self.failed
+   }
+
+   /// Whether the last object is done, or there are no objects at all
    pub fn is_done(&self) -> bool {
-      self.draw_start_index >= self.hitobjects.len() - 1
-         && self.hitobjects.get(self.hitobjects.len() - 1).unwrap().hit_state().is_done()
+      self.hitobjects.last().map_or(true, |last| {
+         self.draw_start_index + 1 >= self.hitobjects.len() && last.hit_state().is_done()
+      })
+   }
+}
+
+/// Milliseconds, which may have a fractional part, rounded to the nearest millisecond
+fn parse_time(fields: &[&str], index: usize) -> Result<Duration, BeatmapErrorKind> {
+   let time = parse_field::<f64>(fields, index)?.round();
+   if time >= 0.0 && time.is_finite() {
+      Ok(Duration::from_millis(time as u64))
+   } else {
+      Err(BeatmapErrorKind::InvalidValue)
+   }
+}
+
+fn parse_field<T: str::FromStr>(fields: &[&str], index: usize) -> Result<T, BeatmapErrorKind> {
+   match fields.get(index) {
+      Some(field) => field.trim().parse().map_err(|_| BeatmapErrorKind::InvalidValue),
+      None => Err(BeatmapErrorKind::MissingField),
+   }
+}
+
+#[derive(Debug)]
+pub struct BeatmapError {
+   pub filename: String,
+   pub section: BeatmapSection,
+   pub line_number: usize,
+   pub line: String,
+   pub kind: BeatmapErrorKind,
+}
+impl fmt::Display for BeatmapError {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      if let BeatmapErrorKind::Io(error) = &self.kind {
+         write!(f, "{}: {}", self.filename, error)
+      } else {
+         write!(f, "{}:{} [{}] {}: {:?}", self.filename, self.line_number, self.section, self.kind, self.line)
+      }
+   }
+}
+impl error::Error for BeatmapError {}
+
+#[derive(Debug)]
+pub enum BeatmapErrorKind {
+   Io(io::Error),
+   UnknownKey,
+   InvalidValue,
+   MissingField,
+   MalformedLine,
+   MissingHeader,
+}
+impl fmt::Display for BeatmapErrorKind {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      use BeatmapErrorKind::*;
+      match self {
+         Io(error) => write!(f, "{}", error),
+         UnknownKey => write!(f, "unknown key"),
+         InvalidValue => write!(f, "invalid value"),
+         MissingField => write!(f, "missing field"),
+         MalformedLine => write!(f, "malformed line"),
+         MissingHeader => write!(f, "missing \"osu file format\" header"),
+      }
    }
 }
 
@@ -343,11 +913,101 @@ pub struct TimingPoint {
    uninherited: isize,
    effects: usize,
 }
+impl TimingPoint {
+   /// Start time in milliseconds, as written in the beatmap
+   pub fn start_time(&self) -> isize {
+      self.start_time
+   }
+   pub fn beat_length(&self) -> f64 {
+      self.beat_length
+   }
+   pub fn bpm(&self) -> f64 {
+      60_000.0 / self.beat_length
+   }
+   pub fn meter(&self) -> isize {
+      self.meter
+   }
+   pub fn sample_set(&self) -> isize {
+      self.sample_set
+   }
+   pub fn sample_index(&self) -> isize {
+      self.sample_index
+   }
+   pub fn volume(&self) -> isize {
+      self.volume
+   }
+   pub fn is_uninherited(&self) -> bool {
+      self.uninherited == 1
+   }
+   pub fn effects(&self) -> usize {
+      self.effects
+   }
+   /// Inherited timing points store the slider velocity as a negative inverse percentage
+   pub fn slider_velocity(&self) -> f64 {
+      if self.is_uninherited() || self.beat_length >= 0.0 {
+         1.0
+      } else {
+         (-100.0 / self.beat_length).clamp(MIN_SLIDER_VELOCITY, MAX_SLIDER_VELOCITY)
+      }
+   }
+}
 
+#[derive(Debug, Clone, Default)]
 pub struct BeatmapColour {
-   combo_colour: Colour<u8>,
-   slider_track_override_colour: Colour<u8>,
-   slider_border_colour: Colour<u8>,
+   pub combo_colours: Vec<Colour<u8>>,
+   pub slider_track_override_colour: Option<Colour<u8>>,
+   pub slider_border_colour: Option<Colour<u8>>,
+}
+impl BeatmapColour {
+   /// The beatmap's combo colours, or the default skin ones if it doesn't have any
+   pub fn combo_colours(&self) -> &[Colour<u8>] {
+      if self.combo_colours.is_empty() {
+         &DEFAULT_COMBO_COLOURS
+      } else {
+         &self.combo_colours
+      }
+   }
+}
+
+#[derive(Debug, Clone, Default)]
+struct HitSample {
+   normal_set: i32,
+   addition_set: i32,
+   index: i32,
+   volume: Volume,
+   filename: String,
+}
+
+/// Parses the optional `normalSet:additionSet:index:volume:filename` field of a hit object
+fn parse_hitsample(fields: &[&str], index: usize) -> Result<HitSample, BeatmapErrorKind> {
+   let field = match fields.get(index) {
+      Some(field) if !field.trim().is_empty() => field.trim(),
+      _ => return Ok(HitSample::default()),
+   };
+   let parts: Vec<&str> = field.splitn(5, ':').collect();
+   let optional = |index| match parts.get(index) {
+      Some(_) => parse_field::<i32>(&parts, index).map_err(|_| BeatmapErrorKind::InvalidValue),
+      None => Ok(0),
+   };
+   let volume = optional(3)?;
+   Ok(HitSample {
+      normal_set: optional(0)?,
+      addition_set: optional(1)?,
+      index: optional(2)?,
+      // 0 means the timing point's volume is used instead
+      volume: if volume > 0 { Volume(volume as f32 / 100.0) } else { Volume::default() },
+      filename: parts.get(4).map_or_else(String::new, |filename| filename.to_string()),
+   })
+}
+
+fn parse_colour(value: &str) -> Option<Colour<u8>> {
+   let rgb = parse_list(value, ",");
+   Some(Colour {
+      r: parse_field(&rgb, 0).ok()?,
+      g: parse_field(&rgb, 1).ok()?,
+      b: parse_field(&rgb, 2).ok()?,
+      a: u8::MAX,
+   })
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, IntoEnumIterator)]
@@ -371,3 +1031,317 @@ impl fmt::Display for BeatmapSection {
       write!(f, "{:?}", self)
    }
 }
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use assert_approx_eq::assert_approx_eq;
+
+   const HEADER: &str = "osu file format v14\n\n[General]\nAudioFilename: audio.mp3\n";
+
+   #[test]
+   fn test_unknown_key_is_warning() {
+      let file = mergestr(HEADER, "SomeFutureKey: 1\nStackLeniency: abc\nTitle:Re:Zero\n");
+      let beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      assert_eq!(2, beatmap.warnings.len());
+      assert_eq!(5, beatmap.warnings[0].line_number);
+      assert_eq!(BeatmapSection::General, beatmap.warnings[0].section);
+      assert!(matches!(beatmap.warnings[0].kind, BeatmapErrorKind::UnknownKey));
+      assert!(matches!(beatmap.warnings[1].kind, BeatmapErrorKind::InvalidValue));
+      assert_eq!("Re:Zero", beatmap.settings.get(&BeatmapSettingName::Title).unwrap().parse_as_str());
+   }
+
+   #[test]
+   fn test_invalid_hitobject_is_warning() {
+      let file = mergestr(HEADER, "\n[HitObjects]\n256,192,1000,1,0\n256,abc,1500,1,0\n256,192\n");
+      let beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      assert_eq!(1, beatmap.hitobjects.len());
+      assert_eq!(2, beatmap.warnings.len());
+      let warning = &beatmap.warnings[0];
+      assert_eq!(8, warning.line_number);
+      assert_eq!(BeatmapSection::HitObjects, warning.section);
+      assert_eq!("256,abc,1500,1,0", warning.line);
+      assert!(matches!(warning.kind, BeatmapErrorKind::InvalidValue));
+      assert!(matches!(beatmap.warnings[1].kind, BeatmapErrorKind::MissingField));
+   }
+
+   #[test]
+   fn test_no_valid_hitobjects() {
+      let file = mergestr(HEADER, "\n[HitObjects]\nabc,192,1000,1,0\n256,192\n");
+      let mut beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      assert!(beatmap.hitobjects.is_empty());
+      assert_eq!(2, beatmap.warnings.len());
+      beatmap.prepare(
+         &PixRect::new_from_sdl2_rect(sdl2::rect::Rect::new(0, 0, 640, 480)),
+         &OsruGameModsActive::new(),
+      );
+      assert!(beatmap.is_done());
+      assert_eq!(Some(ComboResult::PerfectCombo), beatmap.combo_result());
+   }
+
+   #[test]
+   fn test_decimal_fields() {
+      let file = mergestr(
+         HEADER,
+         "\n[TimingPoints]\n1234.5,500,4,1,0,100,1,0\nabc,500\n\n[HitObjects]\n256.5,192,1000.4,1,0\n",
+      );
+      let beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      assert_eq!(1, beatmap.timing_points.len());
+      assert_eq!(1235, beatmap.timing_points[0].start_time());
+      assert_eq!(1, beatmap.warnings.len());
+      assert_eq!(Duration::from_millis(1000) + BEATMAP_TIMING_OFFSET, beatmap.hitobjects[0].time());
+      assert_approx_eq!(256.5, beatmap.hitobjects[0].position().x().get());
+   }
+
+   #[test]
+   fn test_colours() {
+      let file = mergestr(
+         HEADER,
+         "\n[Colours]\nCombo2 : 0,255,0\nCombo1 : 255,0,0\nSliderBorder : 10,20,30\n\n[HitObjects]\n\
+         0,0,1000,5,0\n0,0,1100,1,0\n0,0,1200,5,0\n0,0,1300,37,0\n0,0,1400,1,0\n256,192,1500,8,0,2000\n\
+         0,0,2100,1,0\n",
+      );
+      let mut beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      assert_eq!(2, beatmap.beatmap_colours.combo_colours.len());
+      assert_eq!(255, beatmap.beatmap_colours.combo_colours[0].r);
+      assert_eq!(30, beatmap.beatmap_colours.slider_border_colour.unwrap().b);
+      assert!(beatmap.beatmap_colours.slider_track_override_colour.is_none());
+
+      beatmap.assign_combos();
+      let green: Vec<u8> = beatmap
+         .hitobjects
+         .iter()
+         .map(|hitobj| if let HitObject::HitCircle(c) = hitobj { c.combo_colour.g } else { 0 })
+         .collect();
+      // the fourth object skips two colours, wrapping around to Combo1, and the object after the spinner
+      // starts a new combo without its new combo bit
+      assert_eq!(vec![0, 0, 255, 0, 0, 0, 255], green);
+      let combo_numbers: Vec<u32> = beatmap
+         .hitobjects
+         .iter()
+         .map(|hitobj| if let HitObject::HitCircle(c) = hitobj { c.combo_number } else { 0 })
+         .collect();
+      assert_eq!(vec![1, 2, 1, 1, 2, 0, 1], combo_numbers);
+   }
+
+   #[test]
+   fn test_slider() {
+      let file = mergestr(
+         HEADER,
+         "\n[Difficulty]\nSliderMultiplier:1\nSliderTickRate:1\n\n[TimingPoints]\n0,500,4,1,0,100,1,0\n\n\
+          [HitObjects]\n100,100,1000,2,0,L|300:100,2,200\n",
+      );
+      let mut beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      if let HitObject::Slider(slider) = &mut beatmap.hitobjects[0] {
+         slider.prepare(
+            &Playfield::new(&PixRect::new_from_sdl2_rect(sdl2::rect::Rect::new(0, 0, 640, 480))),
+            &beatmap.settings,
+         );
+         assert_eq!(Duration::from_millis(1000), slider.span_duration);
+         assert_eq!(Duration::from_millis(3000) + BEATMAP_TIMING_OFFSET, slider.end_time);
+
+         println!("Testing ticks, repeat and end");
+         let checkpoints: Vec<u128> =
+            slider.checkpoints.iter().map(|time| (*time - BEATMAP_TIMING_OFFSET).as_millis()).collect();
+         assert_eq!(vec![1500, 2000, 2500, 3000], checkpoints);
+
+         println!("Testing ball progress");
+         assert_approx_eq!(0.5, slider.progress_at(slider.time + Duration::from_millis(500)));
+         assert_approx_eq!(0.9, slider.progress_at(slider.time + Duration::from_millis(1100)));
+         assert_approx_eq!(0.0, slider.progress_at(slider.end_time));
+
+         println!("Testing the body stays hidden while fading out");
+         let timings = AnimationTiming::default();
+         slider.hit_state = HitState::Hit(HitSuccess::Great);
+         slider.current_time = slider.end_time + Duration::from_millis(100);
+         slider.colour.a = u8::MAX;
+         assert_eq!(0, slider.body_alpha(&timings, &VisibilityPolicy::Hidden));
+         assert_eq!(u8::MAX, slider.body_alpha(&timings, &VisibilityPolicy::Normal));
+      } else {
+         panic!["expected a slider"];
+      }
+   }
+
+   #[test]
+   fn test_timing_points() {
+      let file = mergestr(
+         HEADER,
+         "\n[Difficulty]\nSliderMultiplier:1\n\n[TimingPoints]\n0,500,4,1,0,100,1,0\n1000,-50,4,1,0,100,0,0\n\
+          2000,400,4,1,0,100,1,0\n\n[HitObjects]\n100,100,1500,2,0,L|300:100,1,200\n",
+      );
+      let mut beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      let offset = beatmap.timing_offset();
+      let at = |ms| Duration::from_millis(ms) + offset;
+      assert_approx_eq!(500.0, beatmap.beat_length_at(at(1500)));
+      assert_approx_eq!(120.0, beatmap.uninherited_timing_point_at(at(500)).unwrap().bpm());
+      assert_approx_eq!(400.0, beatmap.beat_length_at(at(2000)));
+
+      println!("Testing slider velocity");
+      assert_approx_eq!(1.0, beatmap.slider_velocity_at(at(500)));
+      assert_approx_eq!(2.0, beatmap.slider_velocity_at(at(1500)));
+      assert_approx_eq!(1.0, beatmap.slider_velocity_at(at(2500)));
+
+      println!("Testing slider duration");
+      if let HitObject::Slider(slider) = &mut beatmap.hitobjects[0] {
+         slider.prepare(
+            &Playfield::new(&PixRect::new_from_sdl2_rect(sdl2::rect::Rect::new(0, 0, 640, 480))),
+            &beatmap.settings,
+         );
+         assert_eq!(Duration::from_millis(500), slider.span_duration);
+         assert_eq!(at(2000), slider.end_time());
+      } else {
+         panic!["expected a slider"];
+      }
+   }
+
+   #[test]
+   fn test_hitsamples() {
+      let file = mergestr(
+         HEADER,
+         "\n[HitObjects]\n256,192,1000,1,2,1:2:3:70:hit.wav\n100,100,2000,2,0,L|300:100,1,200,2|8,1:2|0:3,3:0:0:0:\n\
+          256,192,3000,1,0,a:b:0:0:\n",
+      );
+      let beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      if let HitObject::HitCircle(hitcircle) = &beatmap.hitobjects[0] {
+         assert!(hitcircle.hitsounds.whistle);
+         assert_eq!(
+            (1, 2, 3),
+            (hitcircle.hitsample_set, hitcircle.hitsample_additional_set, hitcircle.hitsample_index)
+         );
+         assert_approx_eq!(0.7, hitcircle.hitsample_volume.0);
+         assert_eq!("hit.wav", hitcircle.hitsample_filename);
+      } else {
+         panic!["expected a hit circle"];
+      }
+
+      println!("Testing slider edge sounds and sets");
+      if let HitObject::Slider(slider) = &beatmap.hitobjects[1] {
+         assert_eq!(2, slider.edge_sounds.len());
+         assert!(slider.edge_sounds[0].whistle && slider.edge_sounds[1].clap);
+         assert_eq!(slider::EdgeSet { normal_set: 0, addition_set: 3 }, slider.edge_sets[1]);
+         assert_eq!(3, slider.hitsample_set);
+      } else {
+         panic!["expected a slider"];
+      }
+
+      println!("Testing invalid hitsample");
+      assert_eq!(1, beatmap.warnings.len());
+      assert!(matches!(beatmap.warnings[0].kind, BeatmapErrorKind::InvalidValue));
+   }
+
+   #[test]
+   fn test_stacking() {
+      let file = mergestr(
+         HEADER,
+         "\n[Difficulty]\nApproachRate:5\n\n[General]\nStackLeniency:0.7\n\n[HitObjects]\n\
+          100,100,1000,1,0\n100,100,1100,1,0\n101,101,1200,1,0\n100,100,5000,1,0\n",
+      );
+      let mut beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      beatmap.prepare(
+         &PixRect::new_from_sdl2_rect(sdl2::rect::Rect::new(0, 0, 640, 480)),
+         &OsruGameModsActive::new(),
+      );
+      let stack_heights: Vec<i32> = beatmap.hitobjects.iter().map(HitObject::stack_height).collect();
+      assert_eq!(vec![2, 1, 0, 0], stack_heights);
+      // stacked objects move up and to the left
+      let top = beatmap.hitobjects[3].screen_position();
+      let bottom = beatmap.hitobjects[0].screen_position();
+      assert!(bottom.x().get() < top.x().get() && bottom.y().get() < top.y().get());
+
+      println!("Testing objects stacked on a slider end");
+      let file = mergestr(
+         HEADER,
+         "\n[TimingPoints]\n0,500,4,1,0,100,1,0\n\n[HitObjects]\n0,100,1000,2,0,L|100:100,1,100\n\
+          100,100,1600,1,0\n",
+      );
+      let mut beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      beatmap.prepare(
+         &PixRect::new_from_sdl2_rect(sdl2::rect::Rect::new(0, 0, 640, 480)),
+         &OsruGameModsActive::new(),
+      );
+      let stack_heights: Vec<i32> = beatmap.hitobjects.iter().map(HitObject::stack_height).collect();
+      assert_eq!(vec![0, -1], stack_heights);
+   }
+
+   #[test]
+   fn test_playfield_transforms() {
+      let file = mergestr(HEADER, "\n[HitObjects]\n100,100,1000,1,0\n100,100,2000,2,0,L|200:150,1,100\n");
+      let mut beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      let viewport = PixRect::new_from_sdl2_rect(sdl2::rect::Rect::new(0, 0, 640, 480));
+      let mut mods = OsruGameModsActive::new();
+      mods.enable_game_mod(OsruGameModName::HardRock);
+      beatmap.prepare(&viewport, &mods);
+      assert_approx_eq!(284.0, beatmap.hitobjects[0].position().y().get());
+      if let HitObject::Slider(slider) = &beatmap.hitobjects[1] {
+         assert_approx_eq!(234.0, slider.curve_points[1].y().get());
+         assert!(slider.path.position_at(1.0).y().get() < 284.0);
+      } else {
+         panic!["expected a slider"];
+      }
+
+      println!("Testing preparing again");
+      beatmap.prepare(&viewport, &mods);
+      assert_approx_eq!(284.0, beatmap.hitobjects[0].position().y().get());
+      beatmap.prepare(&viewport, &OsruGameModsActive::new());
+      assert_approx_eq!(100.0, beatmap.hitobjects[0].position().y().get());
+   }
+
+   #[test]
+   fn test_spinner() {
+      let file = mergestr(
+         HEADER,
+         "\n[Difficulty]\nOverallDifficulty:5\n\n[HitObjects]\n256,192,1000,12,0,3000,0:0:0:0:\n",
+      );
+      let mut beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      if let HitObject::Spinner(spinner) = &mut beatmap.hitobjects[0] {
+         assert_eq!(Duration::from_millis(3000) + BEATMAP_TIMING_OFFSET, spinner.end_time);
+         spinner.prepare(
+            &Playfield::new(&PixRect::new_from_sdl2_rect(sdl2::rect::Rect::new(0, 0, 640, 480))),
+            &beatmap.settings,
+         );
+         assert_eq!(5, spinner.required_spins);
+         spinner.rotation = std::f32::consts::TAU * 7.5;
+         assert_eq!(7, spinner.spins());
+         assert_eq!(2, spinner.bonus_spins());
+      } else {
+         panic!["expected a spinner"];
+      }
+
+      let file = mergestr(HEADER, "\n[HitObjects]\n256,192,1000,12,0\n");
+      let beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      assert!(beatmap.hitobjects.is_empty());
+      assert!(matches!(beatmap.warnings[0].kind, BeatmapErrorKind::MissingField));
+   }
+
+   #[test]
+   fn test_breaks() {
+      let file = mergestr(HEADER, "\n[Events]\n2,10000,15000\nBreak,30000,32000\n2,abc,1\n");
+      let beatmap = Beatmap::parse("test.osu", &file).unwrap();
+      assert_eq!(2, beatmap.event_breaks.len());
+      assert_eq!(1, beatmap.warnings.len());
+
+      let in_break = Duration::from_millis(12_000) + BEATMAP_TIMING_OFFSET;
+      let after_break = Duration::from_millis(15_000) + BEATMAP_TIMING_OFFSET;
+      assert_eq!(Duration::from_millis(3000), beatmap.break_at(in_break).unwrap().remaining(in_break));
+      assert!(beatmap.break_at(after_break).is_none());
+      assert_eq!(BREAK_BACKGROUND_DIM, beatmap.background_dim(in_break));
+      assert_eq!(BACKGROUND_DIM, beatmap.background_dim(after_break));
+   }
+
+   #[test]
+   fn test_format_version() {
+      let beatmap = Beatmap::parse("test.osu", HEADER).unwrap();
+      assert_eq!(14, beatmap.format_version());
+      assert_eq!(BEATMAP_TIMING_OFFSET, beatmap.timing_offset());
+
+      let file = "\u{feff}osu file format v3\n\n[Difficulty]\nOverallDifficulty:8\n\n[HitObjects]\n256,192,1000,1,0\n";
+      let beatmap = Beatmap::parse("test.osu", file).unwrap();
+      assert_eq!(3, beatmap.format_version());
+      assert_eq!(OsruAR(8.0), beatmap.settings.approach_rate());
+      assert_eq!(Duration::from_millis(1024) + BEATMAP_TIMING_OFFSET, beatmap.hitobjects[0].time());
+
+      let beatmap = Beatmap::parse("test.osu", "[General]\nMode: 0\n").unwrap();
+      assert_eq!(LATEST_FORMAT_VERSION, beatmap.format_version());
+      assert!(matches!(beatmap.warnings[0].kind, BeatmapErrorKind::MissingHeader));
+   }
+}
@@ -0,0 +1,388 @@
+use crate::beatmap::hitobject::*;
+use crate::beatmap::timing::*;
+use crate::beatmap::Beatmap;
+use crate::game::combo::Combo;
+use crate::game::gamemod::*;
+use crate::game::grade::*;
+use crate::global::pixel::*;
+
+use std::time::Duration;
+
+// strains are summed over sections of this many milliseconds, the hardest sections weighing the most
+pub const STRAIN_SECTION_LENGTH: f64 = 400.0;
+pub const STRAIN_DECAY_WEIGHT: f64 = 0.9;
+// objects closer than this in milliseconds are treated as this far apart
+pub const MIN_STRAIN_TIME: f64 = 50.0;
+
+pub const AIM_SKILL_MULTIPLIER: f64 = 26.25;
+pub const AIM_STRAIN_DECAY_BASE: f64 = 0.15;
+pub const SPEED_SKILL_MULTIPLIER: f64 = 1400.0;
+pub const SPEED_STRAIN_DECAY_BASE: f64 = 0.3;
+// speed gets a bonus for objects less than this many milliseconds apart
+pub const SPEED_BONUS_THRESHOLD: f64 = 95.0;
+// distance in normalised osu!pixels past which objects count as a stream rather than a jump
+pub const SINGLE_SPACING_THRESHOLD: f64 = 125.0;
+// distances are scaled so that circles of this radius in osu!pixels keep their size
+pub const NORMALISED_RADIUS: f64 = 52.0;
+// circles smaller than this radius in osu!pixels get a bonus
+pub const SMALL_CIRCLE_RADIUS: f64 = 30.0;
+pub const DIFFICULTY_MULTIPLIER: f64 = 0.0675;
+
+pub const PP_MULTIPLIER: f64 = 1.12;
+
+/// Difficulty of a beatmap under a set of mods, with what the performance calculation needs to know about it
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct DifficultyAttributes {
+   pub aim_rating: f64,
+   pub speed_rating: f64,
+   pub star_rating: f64,
+   /// AR with the mods and playback rate applied, can go past 10
+   pub approach_rate: f64,
+   /// OD with the mods and playback rate applied, can go past 10
+   pub overall_difficulty: f64,
+   pub circle_size: f64,
+   pub max_combo: u32,
+   pub circle_count: u32,
+   pub object_count: u32,
+}
+
+/// Performance points of a play, split by skill
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Performance {
+   pub aim: f64,
+   pub speed: f64,
+   pub accuracy: f64,
+   pub total: f64,
+}
+
+/// One object as seen by the strain skills, in milliseconds after the playback rate and in osu!pixels
+/// normalised by the circle size
+struct DifficultyObject {
+   time: f64,
+   strain_time: f64,
+   jump_distance: f64,
+   travel_distance: f64,
+}
+
+/// Strain that rises with every object and decays over time, with the peak of every section kept
+struct StrainSkill {
+   skill_multiplier: f64,
+   strain_decay_base: f64,
+   current_strain: f64,
+   previous_time: f64,
+   section_end: Option<f64>,
+   section_peak: f64,
+   peaks: Vec<f64>,
+}
+impl StrainSkill {
+   fn new(skill_multiplier: f64, strain_decay_base: f64) -> StrainSkill {
+      StrainSkill {
+         skill_multiplier,
+         strain_decay_base,
+         current_strain: 1.0,
+         previous_time: 0.0,
+         section_end: None,
+         section_peak: 0.0,
+         peaks: vec![],
+      }
+   }
+
+   fn decay(&self, milliseconds: f64) -> f64 {
+      self.strain_decay_base.powf(milliseconds / 1000.0)
+   }
+
+   fn process(&mut self, object: &DifficultyObject, strain_value: f64) {
+      let mut section_end =
+         self.section_end.unwrap_or((object.time / STRAIN_SECTION_LENGTH).ceil() * STRAIN_SECTION_LENGTH);
+      while object.time > section_end {
+         self.peaks.push(self.section_peak);
+         // the next section starts with the strain left over from the last object
+         self.section_peak = self.current_strain * self.decay(section_end - self.previous_time);
+         section_end += STRAIN_SECTION_LENGTH;
+      }
+      self.section_end = Some(section_end);
+
+      self.current_strain *= self.decay(object.strain_time);
+      self.current_strain += strain_value * self.skill_multiplier;
+      self.section_peak = self.section_peak.max(self.current_strain);
+      self.previous_time = object.time;
+   }
+
+   /// Section peaks summed from the hardest down, each weighing `STRAIN_DECAY_WEIGHT` of the one before
+   fn difficulty(&self) -> f64 {
+      let mut peaks = self.peaks.clone();
+      peaks.push(self.section_peak);
+      peaks.sort_by(|a, b| b.partial_cmp(a).unwrap());
+      let mut weight = 1.0;
+      let mut difficulty = 0.0;
+      for peak in peaks {
+         difficulty += peak * weight;
+         weight *= STRAIN_DECAY_WEIGHT;
+      }
+      difficulty
+   }
+}
+
+fn aim_strain(object: &DifficultyObject) -> f64 {
+   (object.jump_distance.powf(0.99) + object.travel_distance.powf(0.99)) / object.strain_time
+}
+
+fn speed_strain(object: &DifficultyObject) -> f64 {
+   let distance = (object.jump_distance + object.travel_distance).min(SINGLE_SPACING_THRESHOLD);
+   let speed_bonus = if object.strain_time < SPEED_BONUS_THRESHOLD {
+      1.0 + 0.75 * ((SPEED_BONUS_THRESHOLD - object.strain_time) / 40.0).powi(2)
+   } else {
+      1.0
+   };
+   speed_bonus * (0.95 + (distance / SINGLE_SPACING_THRESHOLD).powf(3.5)) / object.strain_time
+}
+
+/// AR whose preempt time is `preempt`, the inverse of `OsruAR::preempt_time`
+fn approach_rate_from_preempt(preempt: Duration) -> f64 {
+   let preempt = preempt.as_secs_f64();
+   if preempt > 1.2 {
+      5.0 - (preempt - 1.2) / 0.6 * 5.0
+   } else {
+      5.0 + (1.2 - preempt) / 0.75 * 5.0
+   }
+}
+
+/// OD whose Great window is `timing_great`, the inverse of `OsruOD::timing_great`
+fn overall_difficulty_from_window(timing_great: Duration) -> f64 {
+   (TIMING_WINDOW_GREAT.as_secs_f64() - timing_great.as_secs_f64())
+      / TIMING_WINDOW_GREAT_MULTIPLIER.as_secs_f64()
+}
+
+/// Aim and speed strain of the beatmap's objects with `mods` applied, combined into a star rating.
+/// Works on a beatmap that hasn't been prepared yet.
+pub fn calculate_difficulty(beatmap: &Beatmap, mods: &OsruGameModsActive) -> DifficultyAttributes {
+   let settings = beatmap.settings.with_mods(mods);
+   let rate = mods.playback_rate();
+   let mut hitobjects = beatmap.hitobjects.clone();
+   for hitobj in hitobjects.iter_mut() {
+      hitobj.prepare_timing(&settings);
+   }
+
+   let radius = settings.circle_size().radius().get() as f64;
+   let mut scaling = NORMALISED_RADIUS / radius;
+   if radius < SMALL_CIRCLE_RADIUS {
+      scaling *= 1.0 + (SMALL_CIRCLE_RADIUS - radius).min(5.0) / 50.0;
+   }
+   let milliseconds = |time: Duration| time.as_secs_f64() * 1000.0 / rate;
+   let distance = |from: Pix2D, to: Pix2D| {
+      let diff = to - from;
+      diff.x().get().hypot(diff.y().get()) as f64 * scaling
+   };
+
+   let mut aim = StrainSkill::new(AIM_SKILL_MULTIPLIER, AIM_STRAIN_DECAY_BASE);
+   let mut speed = StrainSkill::new(SPEED_SKILL_MULTIPLIER, SPEED_STRAIN_DECAY_BASE);
+   for pair in hitobjects.windows(2) {
+      let (previous, current) = (&pair[0], &pair[1]);
+      let spinner = previous.is_spinner() || current.is_spinner();
+      let (jump_distance, travel_distance) = match previous {
+         _ if spinner => (0.0, 0.0),
+         HitObject::Slider(slider) => {
+            // the cursor follows the path once per slide
+            let travel_distance = slider.path.length() as f64 * slider.num_slides as f64 * scaling;
+            (distance(previous.end_position(), current.position()), travel_distance)
+         }
+         _ => (distance(previous.position(), current.position()), 0.0),
+      };
+      let object = DifficultyObject {
+         time: milliseconds(current.time()),
+         strain_time: (milliseconds(current.time()) - milliseconds(previous.time())).max(MIN_STRAIN_TIME),
+         jump_distance,
+         travel_distance,
+      };
+      aim.process(&object, aim_strain(&object));
+      speed.process(&object, speed_strain(&object));
+   }
+
+   let aim_rating = aim.difficulty().sqrt() * DIFFICULTY_MULTIPLIER;
+   let speed_rating = speed.difficulty().sqrt() * DIFFICULTY_MULTIPLIER;
+   let perfect_play: Vec<Judgement> = hitobjects.iter().flat_map(HitObject::perfect_judgements).collect();
+   let timings = AnimationTiming::new_from(settings.overall_difficulty(), settings.approach_rate());
+   DifficultyAttributes {
+      aim_rating,
+      speed_rating,
+      star_rating: aim_rating + speed_rating + (aim_rating - speed_rating).abs() / 2.0,
+      approach_rate: approach_rate_from_preempt(timings.preempt_duration().div_f64(rate)),
+      overall_difficulty: overall_difficulty_from_window(timings.timing_great_duration().div_f64(rate)),
+      circle_size: settings.circle_size().0,
+      max_combo: Combo::new(&perfect_play).max_possible(),
+      circle_count: hitobjects.iter().filter(|hitobj| matches!(hitobj, HitObject::HitCircle(_))).count()
+         as u32,
+      object_count: hitobjects.len() as u32,
+   }
+}
+
+/// Rough performance points of a play with `hit_counts` and a highest combo of `max_combo` on a beatmap
+/// with `attributes`, calculated with the same `mods`
+pub fn calculate_pp(
+   attributes: &DifficultyAttributes, hit_counts: &HitCounts, max_combo: u32, mods: &OsruGameModsActive,
+) -> Performance {
+   use OsruGameModName::*;
+   let total_hits = hit_counts.total().max(1) as f64;
+   let accuracy = accuracy(hit_counts);
+   let ar = attributes.approach_rate;
+   let od = attributes.overall_difficulty;
+   let hidden = mods.is_enabled(Hidden);
+
+   let length_bonus = 0.95
+      + 0.4 * (total_hits / 2000.0).min(1.0)
+      + if total_hits > 2000.0 { (total_hits / 2000.0).log10() * 0.5 } else { 0.0 };
+   let miss_penalty = 0.97_f64.powi(hit_counts.miss as i32);
+   let combo_scaling = if attributes.max_combo > 0 {
+      (max_combo as f64 / attributes.max_combo as f64).powf(0.8).min(1.0)
+   } else {
+      1.0
+   };
+   let ar_factor = if ar > 10.33 {
+      1.0 + 0.3 * (ar - 10.33)
+   } else if ar < 8.0 {
+      1.0 + 0.01 * (8.0 - ar)
+   } else {
+      1.0
+   };
+   let skill_value =
+      |rating: f64| (5.0 * (rating / DIFFICULTY_MULTIPLIER).max(1.0) - 4.0).powi(3) / 100_000.0;
+
+   let mut aim = skill_value(attributes.aim_rating) * length_bonus * miss_penalty * combo_scaling * ar_factor;
+   if hidden {
+      aim *= 1.0 + 0.04 * (12.0 - ar);
+   }
+   if mods.is_enabled(FlashLight) {
+      aim *= 1.0 + 0.35 * (total_hits / 200.0).min(1.0);
+   }
+   aim *= 0.5 + accuracy / 2.0;
+   aim *= 0.98 + od.powi(2) / 2500.0;
+
+   let mut speed = skill_value(attributes.speed_rating) * length_bonus * miss_penalty * combo_scaling;
+   if ar > 10.33 {
+      speed *= ar_factor;
+   }
+   if hidden {
+      speed *= 1.0 + 0.04 * (12.0 - ar);
+   }
+   speed *= 0.02 + accuracy;
+   speed *= 0.96 + od.powi(2) / 1600.0;
+
+   // only circles have a hit window to judge accuracy by, sliders and spinners are assumed to be Greats
+   let circle_count = attributes.circle_count as f64;
+   let circle_accuracy = if circle_count > 0.0 {
+      let other_objects = total_hits - circle_count;
+      let points = (hit_counts.great as f64 - other_objects) * 6.0
+         + hit_counts.good as f64 * 2.0
+         + hit_counts.meh as f64;
+      (points / (circle_count * 6.0)).max(0.0)
+   } else {
+      0.0
+   };
+   let mut accuracy_value =
+      1.52163_f64.powf(od) * circle_accuracy.powi(24) * 2.83 * (circle_count / 1000.0).powf(0.3).min(1.15);
+   if hidden {
+      accuracy_value *= 1.08;
+   }
+   if mods.is_enabled(FlashLight) {
+      accuracy_value *= 1.02;
+   }
+
+   let mut multiplier = PP_MULTIPLIER;
+   if mods.is_enabled(NoFail) {
+      multiplier *= 0.9;
+   }
+   if mods.is_enabled(SpunOut) {
+      multiplier *= 0.95;
+   }
+   if mods.is_enabled(Relax) || mods.is_enabled(AutoPilot) {
+      multiplier = 0.0;
+   }
+   let total = (aim.powf(1.1) + speed.powf(1.1) + accuracy_value.powf(1.1)).powf(1.0 / 1.1) * multiplier;
+   Performance { aim, speed, accuracy: accuracy_value, total }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use assert_approx_eq::assert_approx_eq;
+
+   const HEADER: &str = "osu file format v14\n\n[General]\nAudioFilename: audio.mp3\n";
+
+   fn stream(spacing: usize, interval: usize, od: f64) -> Beatmap {
+      let mut file = format!(
+         "{}\n[Difficulty]\nCircleSize:4\nOverallDifficulty:{}\nApproachRate:9\n\n[HitObjects]\n",
+         HEADER, od
+      );
+      for i in 0..100 {
+         let x = 100 + (i % 2) * spacing;
+         file += &format!("{},192,{},1,0\n", x, 1000 + i * interval);
+      }
+      Beatmap::parse("test.osu", &file).unwrap()
+   }
+
+   #[test]
+   fn test_star_rating() {
+      let mods = OsruGameModsActive::new();
+      let slow = calculate_difficulty(&stream(100, 300, 8.0), &mods);
+      let fast = calculate_difficulty(&stream(100, 150, 8.0), &mods);
+      let jumps = calculate_difficulty(&stream(300, 300, 8.0), &mods);
+      assert!(slow.star_rating > 0.0);
+      assert!(fast.star_rating > slow.star_rating && fast.speed_rating > slow.speed_rating);
+      assert!(jumps.star_rating > slow.star_rating && jumps.aim_rating > slow.aim_rating);
+      assert_eq!(100, slow.max_combo);
+      assert_eq!(100, slow.circle_count);
+
+      println!("Testing mod adjustments");
+      let mut mods = OsruGameModsActive::new();
+      mods.enable_game_mod(OsruGameModName::DoubleTime);
+      let double_time = calculate_difficulty(&stream(100, 300, 8.0), &mods);
+      assert!(double_time.star_rating > slow.star_rating);
+      assert!(double_time.aim_rating > slow.aim_rating && double_time.speed_rating > slow.speed_rating);
+      let mut mods = OsruGameModsActive::new();
+      mods.enable_game_mod(OsruGameModName::HalfTime);
+      assert!(calculate_difficulty(&stream(100, 300, 8.0), &mods).star_rating < slow.star_rating);
+      // 600ms preempt becomes 400ms, 31.5ms Great window becomes 21ms
+      assert_approx_eq!(10.0 + 1.0 / 3.0, double_time.approach_rate);
+      assert_approx_eq!(9.75, double_time.overall_difficulty);
+      let mut mods = OsruGameModsActive::new();
+      mods.enable_game_mod(OsruGameModName::HardRock);
+      let hard_rock = calculate_difficulty(&stream(100, 300, 5.0), &mods);
+      assert_approx_eq!(7.0, hard_rock.overall_difficulty);
+      assert_approx_eq!(5.2, hard_rock.circle_size);
+   }
+
+   #[test]
+   fn test_repeat_sliders() {
+      let sliders = |slides: u32| {
+         let mut file = format!("{}\n[Difficulty]\nCircleSize:4\n\n[HitObjects]\n", HEADER);
+         for i in 0..20 {
+            file += &format!("100,192,{},2,0,L|200:192,{},100\n", 1000 + i * 1000, slides);
+         }
+         Beatmap::parse("test.osu", &file).unwrap()
+      };
+      let mods = OsruGameModsActive::new();
+      let single = calculate_difficulty(&sliders(1), &mods);
+      let repeats = calculate_difficulty(&sliders(3), &mods);
+      assert!(repeats.aim_rating > single.aim_rating);
+   }
+
+   #[test]
+   fn test_pp() {
+      let mods = OsruGameModsActive::new();
+      let attributes = calculate_difficulty(&stream(200, 200, 8.0), &mods);
+      let perfect = calculate_pp(&attributes, &HitCounts { great: 100, ..HitCounts::default() }, 100, &mods);
+      let worse =
+         calculate_pp(&attributes, &HitCounts { great: 90, good: 10, ..HitCounts::default() }, 100, &mods);
+      let missed =
+         calculate_pp(&attributes, &HitCounts { great: 99, miss: 1, ..HitCounts::default() }, 60, &mods);
+      assert!(perfect.total > 0.0);
+      assert!(worse.total < perfect.total && worse.accuracy < perfect.accuracy);
+      assert!(missed.total < perfect.total && missed.aim < perfect.aim);
+
+      let mut no_fail = OsruGameModsActive::new();
+      no_fail.enable_game_mod(OsruGameModName::NoFail);
+      let attributes = calculate_difficulty(&stream(200, 200, 8.0), &no_fail);
+      let hit_counts = HitCounts { great: 100, ..HitCounts::default() };
+      assert_approx_eq!(perfect.total * 0.9, calculate_pp(&attributes, &hit_counts, 100, &no_fail).total);
+   }
+}
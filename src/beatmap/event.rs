@@ -1,5 +1,7 @@
 use crate::global::pixel::*;
 
+use std::time::Duration;
+
 pub struct EventBackground {
    pub start_time: i32,
    pub filename: String,
@@ -11,7 +13,21 @@ pub struct EventVideo {
    offset_from_center: Pix2D,
 }
 
+#[derive(Debug, Copy, Clone)]
 pub struct EventBreak {
-   start_time: i32,
-   end_time: i32,
+   pub start_time: Duration,
+   pub end_time: Duration,
+}
+impl EventBreak {
+   pub fn contains(&self, time: Duration) -> bool {
+      time >= self.start_time && time < self.end_time
+   }
+
+   pub fn duration(&self) -> Duration {
+      self.end_time - self.start_time
+   }
+
+   pub fn remaining(&self, time: Duration) -> Duration {
+      self.end_time.checked_sub(time).unwrap_or_default()
+   }
 }
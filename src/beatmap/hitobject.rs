@@ -1,8 +1,11 @@
 pub mod hitcircle;
 pub mod slider;
+pub mod spinner;
+pub mod visibility;
 
 use super::*;
 use crate::global::pixel::*;
+use crate::global::playfield::*;
 use crate::global::*;
 use crate::input::{self, InputManager, InputUpdate};
 
@@ -10,6 +13,8 @@ use enum_iterator::IntoEnumIterator;
 
 use hitcircle::*;
 use slider::*;
+use spinner::*;
+use visibility::*;
 
 use sdl2::{
    image::LoadTexture,
@@ -26,10 +31,27 @@ pub const COLOUR_MEH: Colour<u8> = Colour { r: 255, g: 159, b: 0, a: 128 };
 pub const COLOUR_MISS: Colour<u8> = Colour { r: 255, g: 39, b: 53, a: 128 };
 pub const COLOUR_ACTIVE: Colour<u8> = Colour { r: 230, g: 230, b: 250, a: 128 };
 
-pub const APPROACH_CIRCLE_MAX_SCALING: f32 = 6.0;
+pub const APPROACH_CIRCLE_MAX_SCALING: f32 = 4.0;
 
-pub const HITCIRCLE_DEFAULT_SCALING: f32 = 2.0;
+// radius in pixels of the circle in a hitcircle texture, sprites are scaled by the object radius over this
+pub const SKIN_CIRCLE_RADIUS: f32 = 64.0;
+pub const HITCIRCLE_HIT_SCALING: f32 = 0.25;
 pub const HITCIRCLE_MAX_OPACITY: u128 = 128;
+pub const COMBO_NUMBER_SCALING: f32 = 0.8;
+// each level of a stack moves an object up and left by this fraction of the circle radius
+pub const STACK_OFFSET_PER_RADIUS: f32 = 0.1;
+
+/// Scaling of skin sprites for objects with the given on-screen radius
+pub fn sprite_scaling(radius: &Pix) -> ScalingFactor {
+   ScalingFactor(radius.get() / SKIN_CIRCLE_RADIUS)
+}
+
+/// Offset in osu!pixels of an object with the given stack height
+pub fn stack_offset(stack_height: i32, beatmap_settings: &BeatmapSettings) -> Pix2D {
+   let radius = beatmap_settings.circle_size().radius().get();
+   let offset = -(stack_height as f32) * radius * STACK_OFFSET_PER_RADIUS;
+   Pix2D::new(Pix::osru_pix(offset), Pix::osru_pix(offset))
+}
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, IntoEnumIterator)]
 pub enum UpdateResult {
@@ -48,10 +70,29 @@ pub enum HitSuccess {
    Great,
    Miss,
 }
+/// Something the player was judged on, in the order it happened
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Judgement {
+   /// A hit circle or spinner was judged as a whole
+   Object(HitSuccess),
+   /// A slider was judged as a whole, its combo comes from the head and checkpoints instead
+   Slider(HitSuccess),
+   /// A slider head, tick, repeat or end was followed
+   SliderTick,
+   /// A slider head, tick or repeat was missed
+   SliderBreak,
+   /// A slider end was missed, which loses its combo without breaking the combo
+   SliderEndMiss,
+   /// A spinner was spun once more past its required spins
+   SpinnerBonus,
+}
+
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub enum HitState {
    NotDrawing,
    Ready,
+   /// Slider head has been judged, the body is still being followed
+   Tracking,
    Hit(HitSuccess),
    DoneDrawing(HitSuccess),
 }
@@ -85,6 +126,7 @@ impl HitState {
       match self {
          NotDrawing => false,
          Ready => true,
+         Tracking => true,
          Hit(_) => true,
          DoneDrawing(_) => false,
       }
@@ -124,31 +166,58 @@ impl Default for HitState {
 pub enum HitObject {
    HitCircle(HitCircle),
    Slider(Slider),
+   Spinner(Spinner),
 }
 impl HitObject {
-   pub fn update(&mut self, update: &InputUpdate, timing: &AnimationTiming) -> UpdateResult {
+   /// Judgements made during the update are pushed onto `judgements`
+   pub fn update(
+      &mut self, update: &InputUpdate, timing: &AnimationTiming, judgements: &mut Vec<Judgement>,
+   ) -> UpdateResult {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.update(update, timing, judgements),
+         Slider(slider) => slider.update(update, timing, judgements),
+         Spinner(spinner) => spinner.update(update, timing, judgements),
+      }
+   }
+
+   pub fn prepare(&mut self, playfield: &Playfield, beatmap_settings: &BeatmapSettings) {
       use HitObject::*;
       match self {
-         HitCircle(hit_circle) => hit_circle.update(update, timing),
-         Slider(slider) => slider.update(update, timing),
+         HitCircle(hit_circle) => hit_circle.prepare(playfield, beatmap_settings),
+         Slider(slider) => slider.prepare(playfield, beatmap_settings),
+         Spinner(spinner) => spinner.prepare(playfield, beatmap_settings),
       }
    }
 
-   pub fn prepare(&mut self, viewport_size: &PixRect, beatmap_settings: &BeatmapSettings) {
+   /// Moves the object in osu!pixels, before it is converted to screen space
+   pub fn apply_transform(&mut self, transform: &PlayfieldTransform) {
       use HitObject::*;
       match self {
-         HitCircle(hit_circle) => hit_circle.prepare(viewport_size, beatmap_settings),
-         Slider(slider) => slider.prepare(viewport_size, beatmap_settings),
+         HitCircle(hit_circle) => hit_circle.position = transform.apply(&hit_circle.position),
+         Slider(slider) => slider.apply_transform(transform),
+         Spinner(spinner) => spinner.position = transform.apply(&spinner.position),
+      }
+   }
+
+   /// Works out everything that depends on time only, before positions are adjusted for stacking
+   pub fn prepare_timing(&mut self, beatmap_settings: &BeatmapSettings) {
+      if let HitObject::Slider(slider) = self {
+         slider.prepare_timing(beatmap_settings);
       }
    }
 
    // fn reset()
 
-   pub fn draw_self(&self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager) -> DrawResult {
+   pub fn draw_self(
+      &self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager, timings: &AnimationTiming,
+      visibility: &VisibilityPolicy,
+   ) -> DrawResult {
       use HitObject::*;
       match self {
-         HitCircle(hit_circle) => hit_circle.draw_self(canvas, texture_manager),
-         Slider(slider) => slider.draw_self(canvas, texture_manager),
+         HitCircle(hit_circle) => hit_circle.draw_self(canvas, texture_manager, timings, visibility),
+         Slider(slider) => slider.draw_self(canvas, texture_manager, timings, visibility),
+         Spinner(spinner) => spinner.draw_self(canvas, texture_manager),
       }
    }
 
@@ -158,6 +227,9 @@ impl HitObject {
    ) {
       use HitObject::*;
 
+      if let Spinner(_) = self {
+         return;
+      }
       let self_time = self.time();
       if current_time < self_time {
          let texture = texture_manager.get(TextureName::ApproachCircle);
@@ -169,9 +241,9 @@ impl HitObject {
          }
          texture.set_alpha_mod(((1.0 - opacity) * 128.0).round() as u8);
 
-         let scaling = (self_time - current_time).as_secs_f32() / timings.preempt_duration().as_secs_f32()
-            * APPROACH_CIRCLE_MAX_SCALING
-            + 2.0;
+         let approach = (self_time - current_time).as_secs_f32() / timings.preempt_duration().as_secs_f32();
+         let scaling =
+            sprite_scaling(&self.radius()).0 * (1.0 + approach * (APPROACH_CIRCLE_MAX_SCALING - 1.0));
 
          let image_size = Pix2D::new(
             Pix::screen_pix(texture.query().width as f32),
@@ -187,11 +259,48 @@ impl HitObject {
       }
    }
 
+   pub fn new_combo(&self) -> bool {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.new_combo,
+         Slider(slider) => slider.new_combo,
+         Spinner(spinner) => spinner.new_combo,
+      }
+   }
+
+   pub fn combo_colours_to_skip(&self) -> u32 {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.combo_colours_to_skip,
+         Slider(slider) => slider.combo_colours_to_skip,
+         Spinner(spinner) => spinner.combo_colours_to_skip,
+      }
+   }
+
+   pub fn set_combo_colour(&mut self, combo_colour: Colour<u8>) {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.set_combo_colour(combo_colour),
+         Slider(slider) => slider.set_combo_colour(combo_colour),
+         Spinner(spinner) => spinner.set_combo_colour(combo_colour),
+      }
+   }
+
+   pub fn set_combo_number(&mut self, combo_number: u32) {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.combo_number = combo_number,
+         Slider(slider) => slider.combo_number = combo_number,
+         Spinner(spinner) => spinner.combo_number = combo_number,
+      }
+   }
+
    pub fn hit_state(&self) -> HitState {
       use HitObject::*;
       match self {
          HitCircle(hit_circle) => hit_circle.hit_state(),
          Slider(slider) => slider.hit_state(),
+         Spinner(spinner) => spinner.hit_state(),
       }
    }
 
@@ -200,28 +309,88 @@ impl HitObject {
       match self {
          HitCircle(hit_circle) => hit_circle.time(),
          Slider(slider) => slider.time(),
+         Spinner(spinner) => spinner.time(),
       }
    }
 
-   pub fn screen_position(&self) -> Pix2D {
+   pub fn end_time(&self) -> Duration {
       use HitObject::*;
       match self {
-         HitCircle(hit_circle) => hit_circle.screen_position(),
-         Slider(slider) => slider.screen_position(),
+         HitCircle(hit_circle) => hit_circle.time(),
+         Slider(slider) => slider.end_time(),
+         Spinner(spinner) => spinner.end_time,
+      }
+   }
+
+   /// Judgements the object gives when played perfectly, in order
+   pub fn perfect_judgements(&self) -> Vec<Judgement> {
+      let mut judgements = vec![];
+      if let HitObject::Slider(slider) = self {
+         // the head and every checkpoint
+         judgements.resize(slider.checkpoints.len() + 1, Judgement::SliderTick);
+         judgements.push(Judgement::Slider(HitSuccess::Great));
+      } else {
+         judgements.push(Judgement::Object(HitSuccess::Great));
+      }
+      judgements
+   }
+
+   pub fn is_spinner(&self) -> bool {
+      matches!(self, HitObject::Spinner(_))
+   }
+
+   /// Position in osu!pixels, not including the stack offset
+   pub fn position(&self) -> Pix2D {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.position,
+         Slider(slider) => slider.position(),
+         Spinner(spinner) => spinner.position,
+      }
+   }
+
+   pub fn end_position(&self) -> Pix2D {
+      use HitObject::*;
+      match self {
+         Slider(slider) => slider.end_position(),
+         _ => self.position(),
+      }
+   }
+
+   pub fn stack_height(&self) -> i32 {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.stack_height,
+         Slider(slider) => slider.stack_height,
+         Spinner(_) => 0,
       }
    }
-}
 
-/*
+   pub fn set_stack_height(&mut self, stack_height: i32) {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.stack_height = stack_height,
+         Slider(slider) => slider.stack_height = stack_height,
+         Spinner(_) => {}
+      }
+   }
 
-pub struct Spinner {
-   pub position: Pix2D,
-   pub time: Duration,
-   pub new_combo: bool,
-   pub combo_colours_to_skip: u32,
-   pub hitsounds: OsruHitSounds,
-   pub end_time: Duration,
+   /// On-screen circle radius, zero for spinners
+   pub fn radius(&self) -> Pix {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.radius,
+         Slider(slider) => slider.radius,
+         Spinner(_) => Pix::screen_pix(0.0),
+      }
+   }
 
-   hit_sample: Vec<i32>,
+   pub fn screen_position(&self) -> Pix2D {
+      use HitObject::*;
+      match self {
+         HitCircle(hit_circle) => hit_circle.screen_position(),
+         Slider(slider) => slider.screen_position(),
+         Spinner(spinner) => spinner.screen_position(),
+      }
+   }
 }
-*/
@@ -16,14 +16,20 @@ pub struct HitCircle {
    pub hitsample_filename: String,
 
    pub hit_state: HitState,
+   pub stack_height: i32,
+   pub combo_colour: Colour<u8>,
+   pub combo_number: u32,
    pub colour: Colour<u8>,
    pub scale: ScalingFactor,
+   pub radius: Pix,
    pub screen_position: Pix2D,
    pub time_hit: Duration,
    pub current_time: Duration,
 }
 impl HitCircle {
-   pub fn update(&mut self, update: &InputUpdate, timings: &AnimationTiming) -> UpdateResult {
+   pub fn update(
+      &mut self, update: &InputUpdate, timings: &AnimationTiming, judgements: &mut Vec<Judgement>,
+   ) -> UpdateResult {
       use HitState::*;
       use HitSuccess::*;
       use UpdateResult::*;
@@ -46,11 +52,11 @@ impl HitCircle {
          self.colour.a = HITCIRCLE_MAX_OPACITY as u8;
          if timings.is_timing_meh(self.time, self.current_time)
             && (update.K1M1_pressed() || update.K2M2_pressed())
-            && cursor_in_range(&self.screen_position, update.current_mouse_pos(), &Pix::ScreenPix(150.0))
+            && cursor_in_range(&self.screen_position, update.current_mouse_pos(), &self.radius)
          {
             self.hit_state = Hit(Meh);
             self.colour = COLOUR_MEH;
-            self.scale = ScalingFactor(0.5);
+            self.scale = ScalingFactor(HITCIRCLE_HIT_SCALING);
             self.time_hit = self.current_time;
 
             if timings.is_timing_great(self.time, self.current_time) {
@@ -60,13 +66,15 @@ impl HitCircle {
                self.hit_state = Hit(Good);
                self.colour = COLOUR_GOOD;
             }
+            judgements.push(Judgement::Object(self.hit_state.hit_success()));
             self.fade_out(&timings);
             return InputConsumed;
          } else if timings.is_timing_miss(self.time, self.current_time) {
             self.hit_state = Hit(Miss);
             self.colour = COLOUR_MISS;
-            self.scale = ScalingFactor(0.5);
+            self.scale = ScalingFactor(HITCIRCLE_HIT_SCALING);
             self.time_hit = timings.timing_meh_end(self.time);
+            judgements.push(Judgement::Object(Miss));
             self.fade_out(&timings);
          }
       }
@@ -89,35 +97,62 @@ impl HitCircle {
       self.colour.a = ((num * HITCIRCLE_MAX_OPACITY) / den) as u8;
    }
 
-   pub fn prepare(&mut self, viewport_size: &PixRect, beatmap_settings: &BeatmapSettings) {
-      self.screen_position = osru_pos_to_screen_pos(&self.position, viewport_size);
+   pub fn prepare(&mut self, playfield: &Playfield, beatmap_settings: &BeatmapSettings) {
+      let position = self.position + stack_offset(self.stack_height, beatmap_settings);
+      self.screen_position = playfield.screen_position(&position);
+      self.radius = playfield.screen_length(&beatmap_settings.circle_size().radius());
    }
 
-   pub fn draw_self(&self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager) -> DrawResult {
+   pub fn draw_self(
+      &self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager, timings: &AnimationTiming,
+      visibility: &VisibilityPolicy,
+   ) -> DrawResult {
       use DrawResult::*;
       let texture = texture_manager.get(TextureName::HitCircle);
       let mut texture = texture.borrow_mut();
       if self.hit_state.is_drawing() {
-         texture.set_alpha_mod(self.colour.a);
+         let mut alpha = self.colour.a;
+         if self.hit_state.is_ready() {
+            alpha = apply_opacity(alpha, visibility.circle_opacity(self.time, self.current_time, timings));
+         }
+         texture.set_alpha_mod(alpha);
          texture.set_color_mod(self.colour.r, self.colour.g, self.colour.b);
 
          let image_size = Pix2D::new(
             Pix::screen_pix(texture.query().width as f32),
             Pix::screen_pix(texture.query().height as f32),
          );
+         let sprite_scaling = sprite_scaling(&self.radius);
          let viewport = calculate_texture_viewport(
             &self.screen_position,
             &image_size,
             &PixRect::new_from_sdl2_rect(canvas.viewport()),
-            self.scale,
+            ScalingFactor(sprite_scaling.0 * self.scale.0),
          );
          canvas.copy(&texture, None, viewport.to_sdl2_rect()).unwrap();
+
+         if self.hit_state.is_ready() {
+            draw_number(
+               canvas,
+               texture_manager,
+               self.combo_number.into(),
+               TextureName::default_digit,
+               &self.screen_position,
+               ScalingFactor(sprite_scaling.0 * COMBO_NUMBER_SCALING),
+               Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: alpha },
+            );
+         }
          Drawed
       } else {
          NotDrawed
       }
    }
 
+   pub fn set_combo_colour(&mut self, combo_colour: Colour<u8>) {
+      self.combo_colour = combo_colour;
+      self.colour = Colour { a: self.colour.a, ..combo_colour };
+   }
+
    pub fn hit_state(&self) -> HitState {
       self.hit_state
    }
@@ -146,8 +181,12 @@ impl Default for HitCircle {
          hitsample_volume: Volume::default(),
          hitsample_filename: nstr(""),
          hit_state: HitState::default(),
+         stack_height: 0,
+         combo_colour: Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: u8::MAX },
+         combo_number: 1,
          colour: Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: 128 },
-         scale: ScalingFactor(2.0),
+         scale: ScalingFactor(1.0),
+         radius: Pix::screen_pix(0.0),
          screen_position: Pix2D::default_screen(),
          time_hit: Duration::default(),
          current_time: Duration::default(),
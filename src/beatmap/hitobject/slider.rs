@@ -1,5 +1,10 @@
+pub mod path;
+
 use super::super::timing::*;
 use super::*;
+use path::SliderPath;
+
+use sdl2::render::BlendMode;
 
 #[derive(Debug, Copy, Eq, PartialEq, Clone, IntoEnumIterator)]
 pub enum SliderCurveType {
@@ -14,9 +19,32 @@ impl Default for SliderCurveType {
    }
 }
 
+pub const SLIDER_BASE_VELOCITY: f64 = 100.0;
+pub const SLIDER_FOLLOW_CIRCLE_SCALING: f32 = 2.4;
+// ticks this close to the end of a span are dropped
+pub const SLIDER_TICK_MIN_DISTANCE_FROM_END: Duration = Duration::from_millis(10);
+
+// the body is drawn by stamping this circle along the path, first as the border then as the track
+pub const SLIDER_BODY_CIRCLE_RADIUS: u32 = 64;
+pub const SLIDER_BODY_STAMPS_PER_RADIUS: f32 = 4.0;
+pub const SLIDER_TRACK_SCALING: f32 = 0.85;
+pub const SLIDER_TRACK_DARKENING: f32 = 0.6;
+pub const SLIDER_DEFAULT_BORDER_COLOUR: Colour<u8> =
+   Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: u8::MAX };
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct EdgeSet {
+   pub normal_set: i32,
+   pub addition_set: i32,
+}
+
 #[derive(Debug, Clone)]
 pub struct Slider {
    pub curve_points: Vec<Pix2D>,
+   pub path: SliderPath,
+   pub beat_length: f64,
+   pub slider_velocity: f64,
+   pub tick_distance_multiplier: f64,
    pub time: Duration,
    pub new_combo: bool,
    pub combo_colours_to_skip: u32,
@@ -24,74 +52,174 @@ pub struct Slider {
    pub num_slides: u32,
    pub length_of_slider: Pix,
 
-   //pub hitsounds: OsruHitSounds,
-   //pub edge_sounds: Vec<i32>,
-   //pub edge_sets: Vec<String>,
+   pub hitsounds: OsruHitSounds,
+   /// Hitsounds of the head, every repeat and the tail
+   pub edge_sounds: Vec<OsruHitSounds>,
+   pub edge_sets: Vec<EdgeSet>,
+   pub hitsample_set: i32,
+   pub hitsample_additional_set: i32,
+   pub hitsample_index: i32,
+   pub hitsample_volume: Volume,
+   pub hitsample_filename: String,
+
+   pub combo_colour: Colour<u8>,
+   pub combo_number: u32,
    pub colour: Colour<u8>,
    pub hit_state: HitState,
+   pub stack_height: i32,
    pub curve_points_screen: Vec<Pix2D>,
+   pub path_screen: SliderPath,
+   pub track_colour_override: Option<Colour<u8>>,
+   pub border_colour: Option<Colour<u8>>,
+   /// Fraction of the body drawn so far while snaking in
+   pub snaking_progress: f32,
    pub length_to_window: Pix,
+   pub span_duration: Duration,
+   pub end_time: Duration,
+   /// Tick positions as a fraction of the path, from the head
+   pub ticks: Vec<f32>,
+   /// Times of every tick, repeat and the slider end
+   pub checkpoints: Vec<Duration>,
+   pub next_checkpoint: usize,
+   pub checkpoints_hit: usize,
 
-   pub active: bool,
+   pub head_hit: bool,
+   pub tracking: bool,
+   pub ball_position: Pix2D,
    pub scale: ScalingFactor,
+   pub radius: Pix,
    pub time_hit: Duration,
+   pub current_time: Duration,
 }
 impl Slider {
-   pub fn update(&mut self, update: &InputUpdate, timings: &AnimationTiming) -> UpdateResult {
+   pub fn update(
+      &mut self, update: &InputUpdate, timings: &AnimationTiming, judgements: &mut Vec<Judgement>,
+   ) -> UpdateResult {
       use HitState::*;
       use HitSuccess::*;
       use UpdateResult::*;
 
-      let current_time = *update.current_time();
+      self.current_time = *update.current_time();
 
       if let DoneDrawing(_) = self.hit_state {
-      } else if current_time < timings.fadein_start(self.time) {
+      } else if self.current_time < timings.fadein_start(self.time) {
          self.hit_state = NotDrawing;
-      } else if current_time < timings.timing_meh_start(self.time) {
+      } else if let Hit(_) = self.hit_state {
+         self.fade_out(timings);
+      } else if let Tracking = self.hit_state {
+         self.track(update, judgements);
+         if self.current_time >= self.end_time {
+            let success = self.judge();
+            judgements.push(Judgement::Slider(success));
+            self.hit_state = Hit(success);
+            self.colour = match success {
+               Great => COLOUR_GREAT,
+               Good => COLOUR_GOOD,
+               Meh => COLOUR_MEH,
+               Miss => COLOUR_MISS,
+            };
+            self.scale = ScalingFactor(HITCIRCLE_HIT_SCALING);
+            self.time_hit = self.end_time;
+            self.fade_out(timings);
+         }
+      } else if self.current_time < timings.timing_meh_start(self.time) {
          self.colour.a = HITCIRCLE_MAX_OPACITY as u8;
          self.hit_state = Ready;
-         if current_time < timings.fadein_end(self.time) {
-            self.fade_in(current_time, &timings);
+         self.snaking_progress = 1.0;
+         if self.current_time < timings.fadein_end(self.time) {
+            self.fade_in(self.current_time, &timings);
          }
-      } else if self.active {
-         if update.K1M1_held() || update.K2M2_held() {}
-      } else if let Hit(_) = self.hit_state {
-         self.fade_out(current_time, &timings);
-      } else if !self.active {
+      } else {
+         self.snaking_progress = 1.0;
          self.hit_state = Ready;
          self.colour.a = HITCIRCLE_MAX_OPACITY as u8;
-         if timings.is_timing_meh(self.time, current_time)
+         if timings.is_timing_meh(self.time, self.current_time)
             && (update.K1M1_pressed() || update.K2M2_pressed())
-            && cursor_in_range(
-               &self.curve_points_screen.get(0).unwrap(),
-               update.current_mouse_pos(),
-               &Pix::ScreenPix(150.0),
-            )
+            && cursor_in_range(&self.screen_position(), update.current_mouse_pos(), &self.radius)
          {
-            self.active = true;
-            self.colour = COLOUR_ACTIVE;
-            self.scale = ScalingFactor(0.5);
-            self.time_hit = current_time;
-
-            if timings.is_timing_great(self.time, current_time) {
-               self.hit_state = Hit(Great);
-            } else if timings.is_timing_good(self.time, current_time) {
-               self.hit_state = Hit(Good);
-            }
-            self.fade_out(current_time, &timings);
-         } else if timings.is_timing_miss(self.time, current_time) {
-            self.hit_state = Hit(Miss);
-            self.colour = COLOUR_MISS;
-            self.scale = ScalingFactor(0.5);
-            self.time_hit = timings.timing_meh_end(self.time);
-            self.fade_out(current_time, &timings);
+            self.hit_state = Tracking;
+            self.head_hit = true;
+            self.tracking = true;
+            judgements.push(Judgement::SliderTick);
+            self.track(update, judgements);
+            return InputConsumed;
+         } else if timings.is_timing_miss(self.time, self.current_time) {
+            self.hit_state = Tracking;
+            self.head_hit = false;
+            judgements.push(Judgement::SliderBreak);
+            self.track(update, judgements);
          }
       }
-      return InputNotConsumed;
+      InputNotConsumed
    }
 
-   pub fn fade_out(&mut self, current_time: Duration, timings: &AnimationTiming) {
-      let num = (current_time - self.time_hit).as_micros();
+   /// Moves the ball and counts the checkpoints passed while the cursor followed it
+   fn track(&mut self, update: &InputUpdate, judgements: &mut Vec<Judgement>) {
+      self.ball_position = self.path_screen.position_at(self.progress_at(self.current_time));
+
+      let holding =
+         update.K1M1_pressed() || update.K2M2_pressed() || update.K1M1_held() || update.K2M2_held();
+      let radius = if self.tracking { self.radius * SLIDER_FOLLOW_CIRCLE_SCALING } else { self.radius };
+      self.tracking = holding && cursor_in_range(&self.ball_position, update.current_mouse_pos(), &radius);
+
+      while let Some(checkpoint) = self.checkpoints.get(self.next_checkpoint) {
+         if *checkpoint > self.current_time {
+            break;
+         }
+         if self.tracking {
+            self.checkpoints_hit += 1;
+            judgements.push(Judgement::SliderTick);
+         } else if self.next_checkpoint + 1 == self.checkpoints.len() {
+            judgements.push(Judgement::SliderEndMiss);
+         } else {
+            judgements.push(Judgement::SliderBreak);
+         }
+         self.next_checkpoint += 1;
+      }
+   }
+
+   /// The head and every checkpoint count equally towards the slider's judgement
+   fn judge(&self) -> HitSuccess {
+      use HitSuccess::*;
+      let total = self.checkpoints.len() + 1;
+      let hit = self.checkpoints_hit + self.head_hit as usize;
+      if hit == total {
+         Great
+      } else if hit * 2 >= total {
+         Good
+      } else if hit > 0 {
+         Meh
+      } else {
+         Miss
+      }
+   }
+
+   /// Position of the ball at `time` as a fraction of the path, from the head
+   pub fn progress_at(&self, time: Duration) -> f32 {
+      if self.span_duration == Duration::from_secs(0) {
+         return 0.0;
+      }
+      let elapsed = cmp::min(time.saturating_sub(self.time), self.end_time - self.time);
+      let spans = elapsed.as_secs_f64() / self.span_duration.as_secs_f64();
+      let span_index = self.span_index(time);
+      let progress = (spans - span_index as f64).min(1.0);
+      if span_index % 2 == 1 {
+         1.0 - progress as f32
+      } else {
+         progress as f32
+      }
+   }
+
+   fn span_index(&self, time: Duration) -> u32 {
+      if self.span_duration == Duration::from_secs(0) {
+         return 0;
+      }
+      let elapsed = time.saturating_sub(self.time).as_secs_f64() / self.span_duration.as_secs_f64();
+      cmp::min(elapsed as u32, self.num_slides.saturating_sub(1))
+   }
+
+   pub fn fade_out(&mut self, timings: &AnimationTiming) {
+      let num = (self.current_time - self.time_hit).as_micros();
       let den = (timings.timing_meh_duration() * 2).as_micros();
       if num > den {
          self.hit_state = self.hit_state.to_done_drawing();
@@ -104,39 +232,277 @@ impl Slider {
       let num = (current_time - timings.fadein_start(self.time)).as_micros();
       let den = (timings.fadein_end(self.time) - timings.fadein_start(self.time)).as_micros();
       self.colour.a = ((num * HITCIRCLE_MAX_OPACITY) / den) as u8;
+      self.snaking_progress = num as f32 / den as f32;
    }
 
-   pub fn prepare(&mut self, viewport_size: &PixRect, beatmap_settings: &BeatmapSettings) {
-      for curve_point in &self.curve_points {
-         self.curve_points_screen.push(osru_pos_to_screen_pos(&curve_point, viewport_size));
+   pub fn prepare(&mut self, playfield: &Playfield, beatmap_settings: &BeatmapSettings) {
+      self.prepare_timing(beatmap_settings);
+
+      let offset = stack_offset(self.stack_height, beatmap_settings);
+      self.curve_points_screen =
+         self.curve_points.iter().map(|point| playfield.screen_position(&(*point + offset))).collect();
+      self.path_screen = self.path.map_points(|point| playfield.screen_position(&(*point + offset)));
+      self.ball_position = self.screen_position();
+      self.radius = playfield.screen_length(&beatmap_settings.circle_size().radius());
+   }
+
+   pub fn apply_transform(&mut self, transform: &PlayfieldTransform) {
+      for curve_point in self.curve_points.iter_mut() {
+         *curve_point = transform.apply(curve_point);
       }
+      self.path = self.path.map_points(|point| transform.apply(point));
+   }
 
-      // TODO: convert slider length to screen coordinates
+   pub fn prepare_timing(&mut self, beatmap_settings: &BeatmapSettings) {
+      // osu!pixels per millisecond
+      let distance_per_beat =
+         SLIDER_BASE_VELOCITY * beatmap_settings.slider_multiplier() * self.slider_velocity;
+      let velocity = distance_per_beat / self.beat_length;
+      let length = self.path.length() as f64;
+      self.span_duration = if velocity > 0.0 {
+         Duration::from_secs_f64(length / velocity / 1000.0)
+      } else {
+         Duration::from_secs(0)
+      };
+      self.end_time = self.time + self.span_duration * self.num_slides;
+      let tick_distance =
+         distance_per_beat * self.tick_distance_multiplier / beatmap_settings.slider_tick_rate();
+      self.prepare_checkpoints(tick_distance, velocity);
    }
 
-   pub fn draw_self(&self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager) -> DrawResult {
+   fn prepare_checkpoints(&mut self, tick_distance: f64, velocity: f64) {
+      let length = self.path.length() as f64;
+      self.ticks.clear();
+      if tick_distance > 0.0 && length > 0.0 {
+         let max_distance = length - velocity * SLIDER_TICK_MIN_DISTANCE_FROM_END.as_millis() as f64;
+         let mut distance = tick_distance;
+         while distance < max_distance {
+            self.ticks.push((distance / length) as f32);
+            distance += tick_distance;
+         }
+      }
+
+      self.checkpoints.clear();
+      for span in 0..self.num_slides {
+         let span_start = self.time + self.span_duration * span;
+         if span % 2 == 0 {
+            for tick in self.ticks.iter() {
+               self.checkpoints.push(span_start + self.span_duration.mul_f32(*tick));
+            }
+         } else {
+            for tick in self.ticks.iter().rev() {
+               self.checkpoints.push(span_start + self.span_duration.mul_f32(1.0 - *tick));
+            }
+         }
+         // repeat, or the slider end for the last span
+         self.checkpoints.push(span_start + self.span_duration);
+      }
+   }
+
+   pub fn draw_self(
+      &self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager, timings: &AnimationTiming,
+      visibility: &VisibilityPolicy,
+   ) -> DrawResult {
       use DrawResult::*;
-      let texture = texture_manager.get(TextureName::HitCircle);
-      let mut texture = texture.borrow_mut();
-      if self.hit_state.is_drawing() {
-         texture.set_alpha_mod(self.colour.a);
-         texture.set_color_mod(self.colour.r, self.colour.g, self.colour.b);
+      use HitState::*;
+      if !self.hit_state.is_drawing() {
+         return NotDrawed;
+      }
+      let body_alpha = self.body_alpha(timings, visibility);
+      self.draw_body(canvas, texture_manager, body_alpha);
 
+      let white = Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: body_alpha };
+      // only the ticks left in the current span are visible
+      let span_index = self.span_index(self.current_time);
+      let span_end = self.time + self.span_duration * (span_index + 1);
+      for checkpoint in self.checkpoints[self.next_checkpoint..].iter().take_while(|time| **time < span_end) {
+         let position = self.path_screen.position_at(self.progress_at(*checkpoint));
+         self.draw_texture(
+            canvas,
+            texture_manager,
+            TextureName::SliderScorePoint,
+            &position,
+            self.scale,
+            white,
+         );
+      }
+
+      if span_index + 1 < self.num_slides && self.snaking_progress >= 1.0 && !self.hit_state.is_hit() {
+         // the arrow sits where the current span ends and points back along the path
+         let (position, angle) = if span_index % 2 == 1 {
+            (self.path_screen.position_at(0.0), self.path_screen.start_angle())
+         } else {
+            (self.path_screen.position_at(1.0), self.path_screen.end_angle())
+         };
+         let degrees = (angle + std::f32::consts::PI).to_degrees() as f64;
+         let texture = texture_manager.get(TextureName::ReverseArrow);
+         let mut texture = texture.borrow_mut();
+         texture.set_alpha_mod(body_alpha);
          let image_size = Pix2D::new(
             Pix::screen_pix(texture.query().width as f32),
             Pix::screen_pix(texture.query().height as f32),
          );
          let viewport = calculate_texture_viewport(
-            &self.curve_points_screen.get(0).unwrap(),
+            &position,
             &image_size,
             &PixRect::new_from_sdl2_rect(canvas.viewport()),
-            self.scale,
+            sprite_scaling(&self.radius),
          );
-         canvas.copy(&texture, None, viewport.to_sdl2_rect()).unwrap();
-         Drawed
-      } else {
-         NotDrawed
+         canvas.copy_ex(&texture, None, viewport.to_sdl2_rect(), degrees, None, false, false).unwrap();
+      }
+
+      match self.hit_state {
+         Ready => {
+            let opacity = visibility.circle_opacity(self.time, self.current_time, timings);
+            let colour = Colour { a: apply_opacity(self.colour.a, opacity), ..self.colour };
+            let position = self.screen_position();
+            self.draw_texture(canvas, texture_manager, TextureName::HitCircle, &position, self.scale, colour);
+            draw_number(
+               canvas,
+               texture_manager,
+               self.combo_number.into(),
+               TextureName::default_digit,
+               &position,
+               ScalingFactor(sprite_scaling(&self.radius).0 * COMBO_NUMBER_SCALING),
+               Colour { a: colour.a, ..white },
+            );
+         }
+         Tracking => {
+            let ball_colour = Colour { a: HITCIRCLE_MAX_OPACITY as u8, ..self.combo_colour };
+            let position = self.ball_position;
+            self.draw_texture(
+               canvas,
+               texture_manager,
+               TextureName::SliderBall,
+               &position,
+               self.scale,
+               ball_colour,
+            );
+            if self.tracking {
+               let colour = Colour { a: HITCIRCLE_MAX_OPACITY as u8, ..white };
+               self.draw_texture(
+                  canvas,
+                  texture_manager,
+                  TextureName::SliderFollowCircle,
+                  &position,
+                  self.scale,
+                  colour,
+               );
+            }
+         }
+         _ => {
+            let position = self.ball_position;
+            self.draw_texture(
+               canvas,
+               texture_manager,
+               TextureName::HitCircle,
+               &position,
+               self.scale,
+               self.colour,
+            );
+         }
+      }
+      Drawed
+   }
+
+   /// Opacity of the body, ticks and reverse arrow, including while fading out after being hit
+   pub fn body_alpha(&self, timings: &AnimationTiming, visibility: &VisibilityPolicy) -> u8 {
+      let opacity = visibility.body_opacity(self.time, self.end_time, self.current_time, timings);
+      apply_opacity(self.colour.a, opacity)
+   }
+
+   /// Draws the border and track into an offscreen buffer first so overlapping stamps don't add up their
+   /// opacity, then draws the buffer with the slider's opacity. Only the body's bounding box of the buffer
+   /// is cleared and copied.
+   fn draw_body(&self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager, alpha: u8) {
+      let radius = self.radius.get();
+      let visible_length = self.path_screen.length() * self.snaking_progress;
+      let step = (radius / SLIDER_BODY_STAMPS_PER_RADIUS).max(1.0);
+
+      let mut stamps = vec![];
+      let mut distance: f32 = 0.0;
+      loop {
+         let position = self.path_screen.position_at_length(distance.min(visible_length));
+         stamps.push((position.x().get_round(), position.y().get_round()));
+         if distance >= visible_length {
+            break;
+         }
+         distance += step;
       }
+
+      let border_colour = self.border_colour.unwrap_or(SLIDER_DEFAULT_BORDER_COLOUR);
+      let track_colour = self.track_colour_override.unwrap_or(Colour {
+         r: (self.combo_colour.r as f32 * SLIDER_TRACK_DARKENING) as u8,
+         g: (self.combo_colour.g as f32 * SLIDER_TRACK_DARKENING) as u8,
+         b: (self.combo_colour.b as f32 * SLIDER_TRACK_DARKENING) as u8,
+         a: u8::MAX,
+      });
+
+      let circle = texture_manager.get(TextureName::SliderBodyCircle);
+      let mut circle = circle.borrow_mut();
+      let buffer = texture_manager.get(TextureName::SliderBodyBuffer);
+      let mut buffer = buffer.borrow_mut();
+
+      let half_size = radius.ceil() as i32 + 1;
+      let min_x = stamps.iter().map(|(x, _)| *x).min().unwrap() - half_size;
+      let min_y = stamps.iter().map(|(_, y)| *y).min().unwrap() - half_size;
+      let max_x = stamps.iter().map(|(x, _)| *x).max().unwrap() + half_size;
+      let max_y = stamps.iter().map(|(_, y)| *y).max().unwrap() + half_size;
+      let buffer_rect = Rect::new(0, 0, buffer.query().width, buffer.query().height);
+      let bounds = Rect::new(min_x, min_y, (max_x - min_x) as u32, (max_y - min_y) as u32);
+      let bounds = match bounds.intersection(buffer_rect) {
+         Some(bounds) => bounds,
+         None => return,
+      };
+
+      canvas
+         .with_texture_canvas(&mut buffer, |buffer_canvas| {
+            // overwrite rather than blend, to clear just the bounding box
+            let blend_mode = buffer_canvas.blend_mode();
+            buffer_canvas.set_blend_mode(BlendMode::None);
+            buffer_canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 0));
+            buffer_canvas.fill_rect(bounds).unwrap();
+            buffer_canvas.set_blend_mode(blend_mode);
+            for (colour, scaling) in [(border_colour, 1.0), (track_colour, SLIDER_TRACK_SCALING)].iter() {
+               circle.set_color_mod(colour.r, colour.g, colour.b);
+               let size = (radius * 2.0 * scaling).round() as u32;
+               for (x, y) in stamps.iter() {
+                  let rect = Rect::new(x - size as i32 / 2, y - size as i32 / 2, size, size);
+                  buffer_canvas.copy(&circle, None, rect).unwrap();
+               }
+            }
+         })
+         .unwrap();
+      buffer.set_alpha_mod(alpha);
+      canvas.copy(&buffer, bounds, bounds).unwrap();
+   }
+
+   fn draw_texture(
+      &self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager, texture_name: TextureName,
+      position: &Pix2D, scale: ScalingFactor, colour: Colour<u8>,
+   ) {
+      let texture = texture_manager.get(texture_name);
+      let mut texture = texture.borrow_mut();
+      texture.set_alpha_mod(colour.a);
+      texture.set_color_mod(colour.r, colour.g, colour.b);
+
+      let image_size = Pix2D::new(
+         Pix::screen_pix(texture.query().width as f32),
+         Pix::screen_pix(texture.query().height as f32),
+      );
+      let scale = ScalingFactor(sprite_scaling(&self.radius).0 * scale.0);
+      let viewport = calculate_texture_viewport(
+         position,
+         &image_size,
+         &PixRect::new_from_sdl2_rect(canvas.viewport()),
+         scale,
+      );
+      canvas.copy(&texture, None, viewport.to_sdl2_rect()).unwrap();
+   }
+
+   pub fn set_combo_colour(&mut self, combo_colour: Colour<u8>) {
+      self.combo_colour = combo_colour;
+      self.colour = Colour { a: self.colour.a, ..combo_colour };
    }
 
    pub fn hit_state(&self) -> HitState {
@@ -147,8 +513,21 @@ impl Slider {
       self.time
    }
 
+   pub fn end_time(&self) -> Duration {
+      self.end_time
+   }
+
+   pub fn position(&self) -> Pix2D {
+      self.curve_points[0]
+   }
+
+   /// Where the last span ends, in osu!pixels
+   pub fn end_position(&self) -> Pix2D {
+      self.path.position_at(if self.num_slides % 2 == 1 { 1.0 } else { 0.0 })
+   }
+
    pub fn screen_position(&self) -> Pix2D {
-      *self.curve_points_screen.get(0).unwrap()
+      *self.curve_points_screen.first().unwrap()
    }
 }
 
@@ -156,21 +535,50 @@ impl Default for Slider {
    fn default() -> Self {
       Slider {
          curve_points: vec![],
+         beat_length: DEFAULT_BEAT_LENGTH,
+         slider_velocity: 1.0,
+         tick_distance_multiplier: 1.0,
          time: Duration::from_secs(0),
          new_combo: false,
          combo_colours_to_skip: 0,
          curve_type: SliderCurveType::default(),
          num_slides: 1,
          length_of_slider: Pix::OsruPix(0.0),
+         path: SliderPath::default(),
+         hitsounds: OsruHitSounds::default(),
+         edge_sounds: vec![],
+         edge_sets: vec![],
+         hitsample_set: 0,
+         hitsample_additional_set: 0,
+         hitsample_index: 0,
+         hitsample_volume: Volume::default(),
+         hitsample_filename: nstr(""),
 
+         combo_colour: Colour { r: 182, g: 39, b: 246, a: u8::MAX },
+         combo_number: 1,
          colour: Colour { r: 182, g: 39, b: 246, a: 128 },
          hit_state: HitState::default(),
+         stack_height: 0,
          curve_points_screen: vec![],
+         path_screen: SliderPath::default(),
+         track_colour_override: None,
+         border_colour: None,
+         snaking_progress: 0.0,
          length_to_window: Pix::OsruPix(0.0),
+         span_duration: Duration::from_secs(0),
+         end_time: Duration::from_secs(0),
+         ticks: vec![],
+         checkpoints: vec![],
+         next_checkpoint: 0,
+         checkpoints_hit: 0,
 
-         active: false,
-         scale: ScalingFactor(2.0),
+         head_hit: false,
+         tracking: false,
+         ball_position: Pix2D::default_screen(),
+         scale: ScalingFactor(1.0),
+         radius: Pix::screen_pix(0.0),
          time_hit: Duration::default(),
+         current_time: Duration::default(),
       }
    }
 }
@@ -0,0 +1,401 @@
+use super::SliderCurveType;
+use crate::global::pixel::*;
+
+use std::f32::consts::{PI, TAU};
+use std::ops;
+
+pub const BEZIER_TOLERANCE: f32 = 0.25;
+pub const CATMULL_DETAIL: usize = 50;
+pub const CIRCULAR_ARC_TOLERANCE: f32 = 0.1;
+
+/// Polyline approximation of a slider curve, parameterised by arc length
+#[derive(Debug, Clone, Default)]
+pub struct SliderPath {
+   points: Vec<Pix2D>,
+   cumulative_length: Vec<f32>,
+}
+impl SliderPath {
+   /// Builds the path from the slider's control points (including its head), truncated or extended
+   /// to `expected_length` when it is positive
+   pub fn new(curve_type: SliderCurveType, control_points: &[Pix2D], expected_length: Pix) -> SliderPath {
+      let pix = match control_points.first() {
+         Some(first) => first.x(),
+         None => return SliderPath::default(),
+      };
+      let control_points: Vec<Vec2> = control_points.iter().map(Vec2::from_pix).collect();
+
+      let mut points = approximate(curve_type, &control_points);
+      let cumulative_length = fit_to_length(&mut points, expected_length.get());
+
+      let points = points.iter().map(|point| point.to_pix(pix)).collect();
+      SliderPath { points, cumulative_length }
+   }
+
+   /// Applies `transform` to every point of the path, e.g. to convert it to screen pixels
+   pub fn map_points<F: Fn(&Pix2D) -> Pix2D>(&self, transform: F) -> SliderPath {
+      let points: Vec<Pix2D> = self.points.iter().map(transform).collect();
+      let vec_points: Vec<Vec2> = points.iter().map(Vec2::from_pix).collect();
+      SliderPath { points, cumulative_length: cumulative_length(&vec_points) }
+   }
+
+   pub fn points(&self) -> &[Pix2D] {
+      &self.points
+   }
+
+   pub fn length(&self) -> f32 {
+      *self.cumulative_length.last().unwrap_or(&0.0)
+   }
+
+   /// Position after travelling `progress` (from 0.0 to 1.0) of the path's length
+   pub fn position_at(&self, progress: f32) -> Pix2D {
+      self.position_at_length(progress.clamp(0.0, 1.0) * self.length())
+   }
+
+   pub fn position_at_length(&self, length: f32) -> Pix2D {
+      if self.points.len() < 2 {
+         return self.points.first().copied().unwrap_or_else(Pix2D::default_osru);
+      }
+      let i = self.cumulative_length.partition_point(|l| *l < length).clamp(1, self.points.len() - 1);
+      let start_length = self.cumulative_length[i - 1];
+      let segment_length = self.cumulative_length[i] - start_length;
+
+      let start = self.points[i - 1];
+      if segment_length <= 0.0 {
+         return start;
+      }
+      let t = ((length - start_length) / segment_length).clamp(0.0, 1.0);
+      let end = self.points[i];
+      Pix2D::new(start.x() + (end.x() - start.x()) * t, start.y() + (end.y() - start.y()) * t)
+   }
+
+   /// Angle in radians of the path's direction at its end, pointing away from the path
+   pub fn end_angle(&self) -> f32 {
+      self.angle_between(self.points.len().saturating_sub(2), self.points.len().saturating_sub(1))
+   }
+
+   /// Angle in radians of the path's direction at its start, pointing away from the path
+   pub fn start_angle(&self) -> f32 {
+      self.angle_between(1, 0)
+   }
+
+   fn angle_between(&self, from: usize, to: usize) -> f32 {
+      match (self.points.get(from), self.points.get(to)) {
+         (Some(from), Some(to)) => {
+            let direction = Vec2::from_pix(to) - Vec2::from_pix(from);
+            direction.y.atan2(direction.x)
+         }
+         _ => 0.0,
+      }
+   }
+}
+
+fn approximate(curve_type: SliderCurveType, control_points: &[Vec2]) -> Vec<Vec2> {
+   use SliderCurveType::*;
+   match curve_type {
+      Linear => control_points.to_vec(),
+      CentripetalCatmullRom => approximate_catmull(control_points),
+      PerfectCircle => {
+         if control_points.len() == 3 {
+            if let Some(points) = approximate_circular_arc(control_points) {
+               return points;
+            }
+         }
+         approximate(Bezier, control_points)
+      }
+      Bezier => {
+         let mut points = vec![];
+         let mut segment_start = 0;
+         for i in 1..=control_points.len() {
+            // a repeated control point starts a new segment
+            if i == control_points.len() || control_points[i] == control_points[i - 1] {
+               let segment = &control_points[segment_start..i];
+               let mut segment_points = approximate_bezier(segment);
+               if !points.is_empty() && !segment_points.is_empty() {
+                  segment_points.remove(0);
+               }
+               points.extend(segment_points);
+               segment_start = i;
+            }
+         }
+         points
+      }
+   }
+}
+
+fn approximate_bezier(control_points: &[Vec2]) -> Vec<Vec2> {
+   if control_points.len() < 3 {
+      return control_points.to_vec();
+   }
+   let mut points = vec![];
+   let mut to_flatten = vec![control_points.to_vec()];
+   while let Some(curve) = to_flatten.pop() {
+      if bezier_is_flat_enough(&curve) {
+         points.push(curve[0]);
+         continue;
+      }
+      let (left, right) = bezier_subdivide(&curve);
+      to_flatten.push(right);
+      to_flatten.push(left);
+   }
+   points.push(*control_points.last().unwrap());
+   points
+}
+
+fn bezier_is_flat_enough(control_points: &[Vec2]) -> bool {
+   for i in 1..control_points.len() - 1 {
+      let second_difference = control_points[i - 1] - control_points[i] * 2.0 + control_points[i + 1];
+      if second_difference.length_squared() > BEZIER_TOLERANCE * BEZIER_TOLERANCE * 4.0 {
+         return false;
+      }
+   }
+   true
+}
+
+/// Splits a bezier curve in half with de Casteljau's algorithm
+fn bezier_subdivide(control_points: &[Vec2]) -> (Vec<Vec2>, Vec<Vec2>) {
+   let count = control_points.len();
+   let mut midpoints = control_points.to_vec();
+   let mut left = Vec::with_capacity(count);
+   let mut right = vec![Vec2::default(); count];
+
+   for i in 0..count {
+      left.push(midpoints[0]);
+      right[count - i - 1] = midpoints[count - i - 1];
+      for j in 0..count - i - 1 {
+         midpoints[j] = (midpoints[j] + midpoints[j + 1]) * 0.5;
+      }
+   }
+   (left, right)
+}
+
+fn approximate_catmull(control_points: &[Vec2]) -> Vec<Vec2> {
+   let count = control_points.len();
+   if count < 2 {
+      return control_points.to_vec();
+   }
+   let mut points = Vec::with_capacity((count - 1) * CATMULL_DETAIL + 1);
+   for i in 0..count - 1 {
+      let v1 = if i > 0 { control_points[i - 1] } else { control_points[i] };
+      let v2 = control_points[i];
+      let v3 = control_points[i + 1];
+      let v4 = if i + 2 < count { control_points[i + 2] } else { v3 * 2.0 - v2 };
+
+      for c in 0..CATMULL_DETAIL {
+         points.push(catmull_point(v1, v2, v3, v4, c as f32 / CATMULL_DETAIL as f32));
+      }
+   }
+   points.push(control_points[count - 1]);
+   points
+}
+
+fn catmull_point(v1: Vec2, v2: Vec2, v3: Vec2, v4: Vec2, t: f32) -> Vec2 {
+   let t2 = t * t;
+   let t3 = t2 * t;
+   (v2 * 2.0
+      + (v3 - v1) * t
+      + (v1 * 2.0 - v2 * 5.0 + v3 * 4.0 - v4) * t2
+      + (v2 * 3.0 - v1 - v3 * 3.0 + v4) * t3)
+      * 0.5
+}
+
+/// Returns `None` when the points are (close to) collinear and don't define a circle
+fn approximate_circular_arc(control_points: &[Vec2]) -> Option<Vec<Vec2>> {
+   let (a, b, c) = (control_points[0], control_points[1], control_points[2]);
+
+   let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+   if d.abs() < 1e-3 {
+      return None;
+   }
+   let a_sq = a.length_squared();
+   let b_sq = b.length_squared();
+   let c_sq = c.length_squared();
+   let centre = Vec2 {
+      x: (a_sq * (b.y - c.y) + b_sq * (c.y - a.y) + c_sq * (a.y - b.y)) / d,
+      y: (a_sq * (c.x - b.x) + b_sq * (a.x - c.x) + c_sq * (b.x - a.x)) / d,
+   };
+
+   let radius = (a - centre).length();
+   let theta_start = (a.y - centre.y).atan2(a.x - centre.x);
+   let mut theta_end = (c.y - centre.y).atan2(c.x - centre.x);
+   while theta_end < theta_start {
+      theta_end += TAU;
+   }
+
+   let mut direction = 1.0;
+   let mut theta_range = theta_end - theta_start;
+   // the middle point decides which way around the circle the arc goes
+   let cross = (b - a).x * (c - a).y - (b - a).y * (c - a).x;
+   if cross < 0.0 {
+      direction = -1.0;
+      theta_range = TAU - theta_range;
+   }
+
+   let amount_points = if 2.0 * radius <= CIRCULAR_ARC_TOLERANCE {
+      2
+   } else {
+      let step = 2.0 * (1.0 - CIRCULAR_ARC_TOLERANCE / radius).acos();
+      ((theta_range / step).ceil() as usize).max(2)
+   };
+
+   let mut points = Vec::with_capacity(amount_points);
+   for i in 0..amount_points {
+      let theta = theta_start + direction * theta_range * i as f32 / (amount_points - 1) as f32;
+      points.push(Vec2 { x: centre.x + radius * theta.cos(), y: centre.y + radius * theta.sin() });
+   }
+   Some(points)
+}
+
+fn cumulative_length(points: &[Vec2]) -> Vec<f32> {
+   let mut cumulative_length = Vec::with_capacity(points.len());
+   let mut length = 0.0;
+   for (i, point) in points.iter().enumerate() {
+      if i > 0 {
+         length += (*point - points[i - 1]).length();
+      }
+      cumulative_length.push(length);
+   }
+   cumulative_length
+}
+
+/// Cuts the path at `expected_length`, or extends its last segment to reach it
+fn fit_to_length(points: &mut Vec<Vec2>, expected_length: f32) -> Vec<f32> {
+   let mut lengths = cumulative_length(points);
+   let calculated_length = *lengths.last().unwrap_or(&0.0);
+   if expected_length <= 0.0 || points.len() < 2 || (calculated_length - expected_length).abs() < f32::EPSILON
+   {
+      return lengths;
+   }
+
+   if calculated_length > expected_length {
+      let end = lengths.partition_point(|l| *l < expected_length);
+      let start_length = lengths[end - 1];
+      let t = (expected_length - start_length) / (lengths[end] - start_length);
+      let cut = points[end - 1] + (points[end] - points[end - 1]) * t;
+
+      points.truncate(end);
+      points.push(cut);
+      lengths.truncate(end);
+      lengths.push(expected_length);
+   } else {
+      let last = points.len() - 1;
+      let direction = points[last] - points[last - 1];
+      let segment_length = direction.length();
+      if segment_length <= 0.0 {
+         return lengths;
+      }
+      let extension = direction * ((expected_length - calculated_length) / segment_length);
+      points[last] = points[last] + extension;
+      lengths[last] = expected_length;
+   }
+   lengths
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+struct Vec2 {
+   x: f32,
+   y: f32,
+}
+impl Vec2 {
+   fn from_pix(pix: &Pix2D) -> Vec2 {
+      Vec2 { x: pix.x().get(), y: pix.y().get() }
+   }
+   fn to_pix(self, kind: Pix) -> Pix2D {
+      match kind {
+         Pix::OsruPix(_) => Pix2D::new(Pix::osru_pix(self.x), Pix::osru_pix(self.y)),
+         Pix::ScreenPix(_) => Pix2D::new(Pix::screen_pix(self.x), Pix::screen_pix(self.y)),
+      }
+   }
+   fn length_squared(self) -> f32 {
+      self.x * self.x + self.y * self.y
+   }
+   fn length(self) -> f32 {
+      self.length_squared().sqrt()
+   }
+}
+impl ops::Add for Vec2 {
+   type Output = Vec2;
+   fn add(self, rhs: Self) -> Self::Output {
+      Vec2 { x: self.x + rhs.x, y: self.y + rhs.y }
+   }
+}
+impl ops::Sub for Vec2 {
+   type Output = Vec2;
+   fn sub(self, rhs: Self) -> Self::Output {
+      Vec2 { x: self.x - rhs.x, y: self.y - rhs.y }
+   }
+}
+impl ops::Mul<f32> for Vec2 {
+   type Output = Vec2;
+   fn mul(self, rhs: f32) -> Self::Output {
+      Vec2 { x: self.x * rhs, y: self.y * rhs }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use assert_approx_eq::assert_approx_eq;
+
+   fn osru(x: f32, y: f32) -> Pix2D {
+      Pix2D::new(Pix::osru_pix(x), Pix::osru_pix(y))
+   }
+
+   #[test]
+   fn test_linear() {
+      let control_points = [osru(0.0, 0.0), osru(100.0, 0.0), osru(100.0, 100.0)];
+      let path = SliderPath::new(SliderCurveType::Linear, &control_points, Pix::osru_pix(200.0));
+      assert_approx_eq!(200.0, path.length());
+      assert_approx_eq!(100.0, path.position_at(0.5).x().get());
+      assert_approx_eq!(50.0, path.position_at(0.75).y().get());
+
+      println!("Testing truncation");
+      let path = SliderPath::new(SliderCurveType::Linear, &control_points, Pix::osru_pix(150.0));
+      assert_approx_eq!(150.0, path.length());
+      assert_approx_eq!(50.0, path.position_at(1.0).y().get());
+
+      println!("Testing extension");
+      let path = SliderPath::new(SliderCurveType::Linear, &control_points, Pix::osru_pix(250.0));
+      assert_approx_eq!(250.0, path.length());
+      assert_approx_eq!(150.0, path.position_at(1.0).y().get());
+   }
+
+   #[test]
+   fn test_perfect_circle() {
+      // half circle of radius 50 around (50, 0)
+      let control_points = [osru(0.0, 0.0), osru(50.0, 50.0), osru(100.0, 0.0)];
+      let path = SliderPath::new(SliderCurveType::PerfectCircle, &control_points, Pix::osru_pix(0.0));
+      assert_approx_eq!(50.0 * PI, path.length(), 0.5);
+      let middle = path.position_at(0.5);
+      assert_approx_eq!(50.0, middle.x().get(), 0.5);
+      assert_approx_eq!(50.0, middle.y().get(), 0.5);
+
+      println!("Testing collinear fallback");
+      let control_points = [osru(0.0, 0.0), osru(50.0, 0.0), osru(100.0, 0.0)];
+      let path = SliderPath::new(SliderCurveType::PerfectCircle, &control_points, Pix::osru_pix(0.0));
+      assert_approx_eq!(100.0, path.length());
+   }
+
+   #[test]
+   fn test_bezier() {
+      // a repeated point splits the curve into two straight segments
+      let control_points = [osru(0.0, 0.0), osru(100.0, 0.0), osru(100.0, 0.0), osru(100.0, 100.0)];
+      let path = SliderPath::new(SliderCurveType::Bezier, &control_points, Pix::osru_pix(0.0));
+      assert_approx_eq!(200.0, path.length());
+      assert_approx_eq!(100.0, path.position_at(0.5).x().get());
+
+      let control_points = [osru(0.0, 0.0), osru(50.0, 100.0), osru(100.0, 0.0)];
+      let path = SliderPath::new(SliderCurveType::Bezier, &control_points, Pix::osru_pix(0.0));
+      let middle = path.position_at(0.5);
+      assert_approx_eq!(50.0, middle.x().get(), 0.5);
+      assert_approx_eq!(50.0, middle.y().get(), 0.5);
+      assert!(path.length() > 100.0 && path.length() < 200.0);
+   }
+
+   #[test]
+   fn test_catmull() {
+      let control_points = [osru(0.0, 0.0), osru(100.0, 0.0)];
+      let path = SliderPath::new(SliderCurveType::CentripetalCatmullRom, &control_points, Pix::osru_pix(0.0));
+      assert_approx_eq!(100.0, path.length(), 0.01);
+      assert_approx_eq!(100.0, path.position_at(1.0).x().get(), 0.01);
+   }
+}
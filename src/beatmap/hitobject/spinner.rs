@@ -0,0 +1,260 @@
+use super::super::timing::*;
+use super::*;
+
+use std::f32::consts::{PI, TAU};
+
+pub const SPINNER_MAX_RPM: f32 = 477.0;
+pub const SPINNER_RPM_SMOOTHING: Duration = Duration::from_millis(250);
+pub const SPINNER_CIRCLE_SCALING: f32 = 1.0;
+pub const SPINNER_APPROACH_CIRCLE_SCALING: f32 = 1.0;
+pub const SPINNER_RPM_SCALING: f32 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct Spinner {
+   pub position: Pix2D,
+   pub time: Duration,
+   pub end_time: Duration,
+   pub new_combo: bool,
+   pub combo_colours_to_skip: u32,
+   pub hitsounds: OsruHitSounds,
+
+   pub hit_state: HitState,
+   pub combo_colour: Colour<u8>,
+   pub combo_number: u32,
+   pub colour: Colour<u8>,
+   pub screen_position: Pix2D,
+   pub required_spins: u32,
+   pub rotation: f32,
+   pub displayed_rotation: f32,
+   pub rpm: f32,
+   pub last_angle: Option<f32>,
+   pub time_hit: Duration,
+   pub current_time: Duration,
+}
+impl Spinner {
+   pub fn update(
+      &mut self, update: &InputUpdate, timings: &AnimationTiming, judgements: &mut Vec<Judgement>,
+   ) -> UpdateResult {
+      use HitState::*;
+      use HitSuccess::*;
+      use UpdateResult::*;
+
+      let previous_time = self.current_time;
+      self.current_time = *update.current_time();
+
+      if let DoneDrawing(_) = self.hit_state {
+      } else if self.current_time < timings.fadein_start(self.time) {
+         self.hit_state = NotDrawing;
+      } else if let Hit(_) = self.hit_state {
+         self.fade_out(timings);
+      } else if self.current_time < self.time {
+         self.hit_state = Ready;
+         self.colour.a = HITCIRCLE_MAX_OPACITY as u8;
+         if self.current_time < timings.fadein_end(self.time) {
+            self.fade_in(self.current_time, timings);
+         }
+      } else if self.current_time < self.end_time {
+         self.hit_state = Ready;
+         self.colour.a = HITCIRCLE_MAX_OPACITY as u8;
+         let holding =
+            update.K1M1_pressed() || update.K2M2_pressed() || update.K1M1_held() || update.K2M2_held();
+         let bonus_spins = self.bonus_spins();
+         self.spin(update.current_mouse_pos(), self.current_time.saturating_sub(previous_time), holding);
+         for _ in bonus_spins..self.bonus_spins() {
+            judgements.push(Judgement::SpinnerBonus);
+         }
+      } else {
+         let success = self.judge();
+         judgements.push(Judgement::Object(success));
+         self.hit_state = Hit(success);
+         self.colour = match success {
+            Great => COLOUR_GREAT,
+            Good => COLOUR_GOOD,
+            Meh => COLOUR_MEH,
+            Miss => COLOUR_MISS,
+         };
+         self.time_hit = self.end_time;
+         self.fade_out(timings);
+      }
+      InputNotConsumed
+   }
+
+   fn spin(&mut self, cursor_pos: &Pix2D, elapsed: Duration, holding: bool) {
+      let offset = *cursor_pos - self.screen_position;
+      let angle = offset.y().get().atan2(offset.x().get());
+
+      let mut delta = 0.0;
+      if let Some(last_angle) = self.last_angle {
+         delta = angle - last_angle;
+         if delta > PI {
+            delta -= TAU;
+         } else if delta < -PI {
+            delta += TAU;
+         }
+      }
+      self.last_angle = Some(angle);
+      if !holding || elapsed == Duration::from_secs(0) {
+         return;
+      }
+
+      let max_delta = SPINNER_MAX_RPM / 60.0 * TAU * elapsed.as_secs_f32();
+      let delta = delta.max(-max_delta).min(max_delta);
+      self.rotation += delta.abs();
+      self.displayed_rotation += delta;
+
+      let rpm = delta.abs() / TAU / elapsed.as_secs_f32() * 60.0;
+      let smoothing = (elapsed.as_secs_f32() / SPINNER_RPM_SMOOTHING.as_secs_f32()).min(1.0);
+      self.rpm += (rpm - self.rpm) * smoothing;
+   }
+
+   fn judge(&self) -> HitSuccess {
+      use HitSuccess::*;
+      let progress = self.progress();
+      if progress >= 1.0 {
+         Great
+      } else if progress > 0.9 {
+         Good
+      } else if progress > 0.75 {
+         Meh
+      } else {
+         Miss
+      }
+   }
+
+   /// Full spins so far
+   pub fn spins(&self) -> u32 {
+      (self.rotation / TAU) as u32
+   }
+
+   /// Spins done past the required amount
+   pub fn bonus_spins(&self) -> u32 {
+      self.spins().saturating_sub(self.required_spins)
+   }
+
+   pub fn progress(&self) -> f32 {
+      if self.required_spins == 0 {
+         1.0
+      } else {
+         self.rotation / TAU / self.required_spins as f32
+      }
+   }
+
+   pub fn fade_out(&mut self, timings: &AnimationTiming) {
+      let num = (self.current_time - self.time_hit).as_micros();
+      let den = (timings.timing_meh_duration() * 2).as_micros();
+      if num > den {
+         self.hit_state = self.hit_state.to_done_drawing();
+      } else {
+         self.colour.a = (HITCIRCLE_MAX_OPACITY - ((num * HITCIRCLE_MAX_OPACITY) / den)) as u8;
+      }
+   }
+
+   pub fn fade_in(&mut self, current_time: Duration, timings: &AnimationTiming) {
+      let num = (current_time - timings.fadein_start(self.time)).as_micros();
+      let den = (timings.fadein_end(self.time) - timings.fadein_start(self.time)).as_micros();
+      self.colour.a = ((num * HITCIRCLE_MAX_OPACITY) / den) as u8;
+   }
+
+   pub fn prepare(&mut self, playfield: &Playfield, beatmap_settings: &BeatmapSettings) {
+      self.screen_position = playfield.screen_position(&self.position);
+      let duration = self.end_time.saturating_sub(self.time).as_secs_f64();
+      self.required_spins = (duration * beatmap_settings.overall_difficulty().spins_per_second()) as u32;
+   }
+
+   pub fn draw_self(&self, canvas: &mut WindowCanvas, texture_manager: &mut TextureManager) -> DrawResult {
+      use DrawResult::*;
+      if !self.hit_state.is_drawing() {
+         return NotDrawed;
+      }
+      let screen_viewport = PixRect::new_from_sdl2_rect(canvas.viewport());
+
+      let texture = texture_manager.get(TextureName::SpinnerCircle);
+      let mut texture = texture.borrow_mut();
+      texture.set_alpha_mod(self.colour.a);
+      texture.set_color_mod(self.colour.r, self.colour.g, self.colour.b);
+      let image_size = Pix2D::new(
+         Pix::screen_pix(texture.query().width as f32),
+         Pix::screen_pix(texture.query().height as f32),
+      );
+      let viewport = calculate_texture_viewport(
+         &self.screen_position,
+         &image_size,
+         &screen_viewport,
+         ScalingFactor(SPINNER_CIRCLE_SCALING),
+      );
+      let degrees = self.displayed_rotation.to_degrees() as f64;
+      canvas.copy_ex(&texture, None, viewport.to_sdl2_rect(), degrees, None, false, false).unwrap();
+
+      if self.current_time >= self.time && self.current_time < self.end_time {
+         let remaining =
+            (self.end_time - self.current_time).as_secs_f32() / (self.end_time - self.time).as_secs_f32();
+         let texture = texture_manager.get(TextureName::SpinnerApproachCircle);
+         let mut texture = texture.borrow_mut();
+         texture.set_alpha_mod(self.colour.a);
+         let image_size = Pix2D::new(
+            Pix::screen_pix(texture.query().width as f32),
+            Pix::screen_pix(texture.query().height as f32),
+         );
+         let viewport = calculate_texture_viewport(
+            &self.screen_position,
+            &image_size,
+            &screen_viewport,
+            ScalingFactor(SPINNER_APPROACH_CIRCLE_SCALING * remaining),
+         );
+         canvas.copy(&texture, None, viewport.to_sdl2_rect()).unwrap();
+
+         let rpm_position =
+            Pix2D::new(self.screen_position.x(), self.screen_position.y() + screen_viewport.height() / 3);
+         draw_number(
+            canvas,
+            texture_manager,
+            self.rpm.round() as u64,
+            TextureName::default_digit,
+            &rpm_position,
+            ScalingFactor(SPINNER_RPM_SCALING),
+            Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: self.colour.a },
+         );
+      }
+      Drawed
+   }
+
+   pub fn set_combo_colour(&mut self, combo_colour: Colour<u8>) {
+      self.combo_colour = combo_colour;
+   }
+
+   pub fn hit_state(&self) -> HitState {
+      self.hit_state
+   }
+
+   pub fn time(&self) -> Duration {
+      self.time
+   }
+
+   pub fn screen_position(&self) -> Pix2D {
+      self.screen_position
+   }
+}
+impl Default for Spinner {
+   fn default() -> Self {
+      Spinner {
+         position: Pix2D::new(Pix::osru_pix(256.0), Pix::osru_pix(192.0)),
+         time: Duration::from_secs(0),
+         end_time: Duration::from_secs(0),
+         new_combo: true,
+         combo_colours_to_skip: 0,
+         hitsounds: OsruHitSounds::default(),
+         hit_state: HitState::default(),
+         combo_colour: Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: u8::MAX },
+         combo_number: 1,
+         colour: Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: 128 },
+         screen_position: Pix2D::default_screen(),
+         required_spins: 0,
+         rotation: 0.0,
+         displayed_rotation: 0.0,
+         rpm: 0.0,
+         last_angle: None,
+         time_hit: Duration::default(),
+         current_time: Duration::default(),
+      }
+   }
+}
@@ -0,0 +1,111 @@
+use super::super::timing::*;
+use crate::game::gamemod::*;
+
+use std::time::Duration;
+
+// with Hidden, objects start fading out once this fraction of the preempt has passed
+pub const HIDDEN_FADEOUT_START: f32 = 0.4;
+// and are fully gone this fraction of the preempt later
+pub const HIDDEN_FADEOUT_DURATION: f32 = 0.3;
+
+/// Decides how visible each part of a hit object is, draw code multiplies its opacity by what this returns
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum VisibilityPolicy {
+   #[default]
+   Normal,
+   Hidden,
+}
+impl VisibilityPolicy {
+   pub fn new(mods: &OsruGameModsActive) -> VisibilityPolicy {
+      if mods.is_enabled(OsruGameModName::Hidden) {
+         VisibilityPolicy::Hidden
+      } else {
+         VisibilityPolicy::Normal
+      }
+   }
+
+   pub fn shows_approach_circle(&self, is_first_object: bool) -> bool {
+      match self {
+         VisibilityPolicy::Normal => true,
+         VisibilityPolicy::Hidden => is_first_object,
+      }
+   }
+
+   /// Opacity from 0.0 to 1.0 of a hit circle or slider head that hasn't been hit yet
+   pub fn circle_opacity(
+      &self, hit_time: Duration, current_time: Duration, timings: &AnimationTiming,
+   ) -> f32 {
+      match self {
+         VisibilityPolicy::Normal => 1.0,
+         VisibilityPolicy::Hidden => {
+            let fadeout_start = Self::fadeout_start(hit_time, timings);
+            let fadeout_duration = timings.preempt_duration().mul_f32(HIDDEN_FADEOUT_DURATION);
+            Self::fade(fadeout_start, fadeout_start + fadeout_duration, current_time)
+         }
+      }
+   }
+
+   /// Opacity from 0.0 to 1.0 of a slider body, which fades out over the whole length of the slider
+   pub fn body_opacity(
+      &self, hit_time: Duration, end_time: Duration, current_time: Duration, timings: &AnimationTiming,
+   ) -> f32 {
+      match self {
+         VisibilityPolicy::Normal => 1.0,
+         VisibilityPolicy::Hidden => {
+            Self::fade(Self::fadeout_start(hit_time, timings), end_time, current_time)
+         }
+      }
+   }
+
+   fn fadeout_start(hit_time: Duration, timings: &AnimationTiming) -> Duration {
+      let preempt = timings.preempt_duration();
+      hit_time.saturating_sub(preempt) + preempt.mul_f32(HIDDEN_FADEOUT_START)
+   }
+
+   fn fade(start: Duration, end: Duration, current_time: Duration) -> f32 {
+      if current_time <= start {
+         1.0
+      } else if current_time >= end {
+         0.0
+      } else {
+         1.0 - (current_time - start).as_secs_f32() / (end - start).as_secs_f32()
+      }
+   }
+}
+
+/// Scales an alpha value by an opacity from `VisibilityPolicy`
+pub fn apply_opacity(alpha: u8, opacity: f32) -> u8 {
+   (alpha as f32 * opacity).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use assert_approx_eq::assert_approx_eq;
+
+   #[test]
+   fn test_hidden() {
+      let timings = AnimationTiming::default(); // 600ms preempt
+      let hit_time = Duration::from_millis(1000);
+      let at = Duration::from_millis;
+
+      let normal = VisibilityPolicy::Normal;
+      assert!(normal.shows_approach_circle(false));
+      assert_eq!(1.0, normal.circle_opacity(hit_time, at(900), &timings));
+
+      let hidden = VisibilityPolicy::Hidden;
+      assert!(hidden.shows_approach_circle(true));
+      assert!(!hidden.shows_approach_circle(false));
+
+      println!("Testing circle fade");
+      assert_eq!(1.0, hidden.circle_opacity(hit_time, at(600), &timings));
+      assert_approx_eq!(0.5, hidden.circle_opacity(hit_time, at(730), &timings));
+      assert_approx_eq!(0.0, hidden.circle_opacity(hit_time, at(820), &timings));
+
+      println!("Testing body fade");
+      let end_time = Duration::from_millis(2640);
+      assert_eq!(1.0, hidden.body_opacity(hit_time, end_time, at(640), &timings));
+      assert_approx_eq!(0.5, hidden.body_opacity(hit_time, end_time, at(1640), &timings));
+      assert_approx_eq!(0.0, hidden.body_opacity(hit_time, end_time, at(2640), &timings));
+   }
+}
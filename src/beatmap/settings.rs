@@ -4,6 +4,11 @@ use std::fmt;
 use super::timing::*;
 use BeatmapSettingName::*;
 
+pub const DEFAULT_DIFFICULTY: f64 = 5.0;
+pub const DEFAULT_SLIDER_MULTIPLIER: f64 = 1.4;
+pub const DEFAULT_SLIDER_TICK_RATE: f64 = 1.0;
+pub const APPROACH_RATE_VERSION: u32 = 8;
+
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, IntoEnumIterator)]
 pub enum BeatmapSettingName {
    Unknown,
@@ -53,13 +58,10 @@ pub enum BeatmapSettingName {
    SliderTickRate,
 }
 impl BeatmapSettingName {
-   pub fn from_str(name: &str) -> BeatmapSettingName {
-      for beatmap_setting_name in BeatmapSettingName::into_enum_iter() {
-         if beatmap_setting_name.to_string() == name {
-            return beatmap_setting_name;
-         }
-      }
-      panic!["BeatmapSettingName {:?} not found", name];
+   pub fn from_str(name: &str) -> Option<BeatmapSettingName> {
+      BeatmapSettingName::into_enum_iter().find(|beatmap_setting_name| {
+         *beatmap_setting_name != Unknown && beatmap_setting_name.to_string() == name
+      })
    }
 }
 
@@ -147,11 +149,47 @@ impl BeatmapSettings {
       self.settings.shrink_to_fit();
    }
 
+   /// Fills in the difficulty settings the file didn't specify. ApproachRate only exists since v8,
+   /// before that (and whenever it is missing) it follows OverallDifficulty.
+   pub fn apply_defaults(&mut self, format_version: u32) {
+      use OsruType::*;
+      let is_missing = |settings: &BeatmapSettings, name| settings.get(&name).unwrap().parse_as_dec() < 0.0;
+
+      for name in [HPDrainRate, CircleSize, OverallDifficulty].iter() {
+         if is_missing(self, *name) {
+            self.set(name, Decimal(DEFAULT_DIFFICULTY));
+         }
+      }
+      if format_version < APPROACH_RATE_VERSION || is_missing(self, ApproachRate) {
+         self.set(&ApproachRate, Decimal(self.overall_difficulty().0));
+      }
+      if is_missing(self, SliderMultiplier) {
+         self.set(&SliderMultiplier, Decimal(DEFAULT_SLIDER_MULTIPLIER));
+      }
+      if is_missing(self, SliderTickRate) {
+         self.set(&SliderTickRate, Decimal(DEFAULT_SLIDER_TICK_RATE));
+      }
+   }
+
+   /// Copy of the settings with the difficulty multipliers of `mods` applied
+   pub fn with_mods(&self, mods: &OsruGameModsActive) -> BeatmapSettings {
+      use OsruType::*;
+      let mut settings = self.clone();
+      settings.set(&ApproachRate, Decimal((self.approach_rate() * mods.ar_multiplier()).0));
+      settings.set(&OverallDifficulty, Decimal((self.overall_difficulty() * mods.od_multiplier()).0));
+      settings.set(&CircleSize, Decimal((self.circle_size() * mods.cs_multiplier()).0));
+      settings.set(&HPDrainRate, Decimal((self.hp_drain_rate() * mods.hp_multiplier()).min(10.0)));
+      settings
+   }
+
+   pub fn letterbox_in_breaks(&self) -> bool {
+      self.get(&LetterboxInBreaks).unwrap().parse_as_int() == 1
+   }
    pub fn hp_drain_rate(&self) -> f64 {
       self.get(&HPDrainRate).unwrap().parse_as_dec()
    }
-   pub fn circle_size(&self) -> f64 {
-      self.get(&CircleSize).unwrap().parse_as_dec()
+   pub fn circle_size(&self) -> OsruCS {
+      OsruCS(self.get(&CircleSize).unwrap().parse_as_dec())
    }
    pub fn overall_difficulty(&self) -> OsruOD {
       OsruOD(self.get(&OverallDifficulty).unwrap().parse_as_dec())
@@ -159,6 +197,9 @@ impl BeatmapSettings {
    pub fn approach_rate(&self) -> OsruAR {
       OsruAR(self.get(&ApproachRate).unwrap().parse_as_dec())
    }
+   pub fn stack_leniency(&self) -> f64 {
+      self.get(&StackLeniency).unwrap().parse_as_dec()
+   }
    pub fn slider_multiplier(&self) -> f64 {
       self.get(&SliderMultiplier).unwrap().parse_as_dec()
    }
@@ -111,6 +111,16 @@ impl OsruOD {
    pub fn timing_meh(&self) -> Duration {
       TIMING_WINDOW_MEH - Duration::from_secs_f64(self.0 * TIMING_WINDOW_MEH_MULTIPLIER.as_secs_f64())
    }
+   /// Spins per second needed to clear a spinner
+   pub fn spins_per_second(&self) -> f64 {
+      if self.0 < 5.0 {
+         2.5 - 1.0 * (5.0 - self.0) / 5.0
+      } else if self.0 > 5.0 {
+         2.5 + 1.25 * (self.0 - 5.0) / 5.0
+      } else {
+         2.5
+      }
+   }
    pub fn mul_unchecked(&self, other: Self) -> Self {
       Self(self.0 * other.0)
    }
@@ -183,24 +193,24 @@ impl OsruAR {
    }
 }
 
-/*
-
-#[derive(Debug, Copy, Clone)]
-pub struct OsruCS(pub f32);
-
-// TODO: perceived_od_mul()
-pub fn ar_multiplier(&self) -> f64 {
-  let mut ar_mul = 1.0;
-  for game_mod in self.mods.iter() {
-    ar_mul *= game_mod.ar_multiplier;
-  }
-  if ar_mul > 10.0 {
-    10.0
-  } else {
-    ar_mul
-  }
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct OsruCS(pub f64);
+impl ops::Mul<f64> for OsruCS {
+   type Output = Self;
+   fn mul(self, rhs: f64) -> Self::Output {
+      let mut result = self.0 * rhs;
+      if result > 10.0 {
+         result = 10.0
+      }
+      OsruCS(result)
+   }
+}
+impl OsruCS {
+   /// Hit circle radius in osu!pixels
+   pub fn radius(&self) -> Pix {
+      Pix::osru_pix((54.4 - 4.48 * self.0) as f32)
+   }
 }
-*/
 
 #[cfg(test)]
 mod tests {
@@ -247,6 +257,11 @@ mod tests {
       assert_eq!(Duration::from_micros(189_500), OsruOD(1.0).timing_meh());
       assert_eq!(Duration::from_micros(199_500), OsruOD(0.0).timing_meh());
 
+      println!("Testing OD spins per second");
+      assert_eq!(1.5, OsruOD(0.0).spins_per_second());
+      assert_eq!(2.5, OsruOD(5.0).spins_per_second());
+      assert_eq!(3.75, OsruOD(10.0).spins_per_second());
+
       println!("Testing od multiplication");
       let a = OsruOD(5.54);
       let b = OsruOD(2.0);
@@ -278,4 +293,13 @@ mod tests {
       assert_eq!(OsruAR(10.0), a * a);
       assert_eq!(OsruAR(25.0), a.mul_unchecked(a));
    }
+   #[test]
+   fn test_cs() {
+      use assert_approx_eq::assert_approx_eq;
+      assert_approx_eq!(54.4, OsruCS(0.0).radius().get());
+      assert_approx_eq!(32.0, OsruCS(5.0).radius().get());
+      assert_approx_eq!(23.04, OsruCS(7.0).radius().get());
+      assert_approx_eq!(5.2, (OsruCS(4.0) * 1.3).0);
+      assert_eq!(OsruCS(10.0), OsruCS(9.0) * 1.3);
+   }
 }
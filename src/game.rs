@@ -1,10 +1,26 @@
 extern crate sdl2;
 
+pub mod combo;
+pub mod flashlight;
+pub mod gamemod;
+pub mod grade;
+pub mod health;
+pub mod results;
+pub mod score;
+
 use crate::global::pixel::*;
 use crate::global::*;
 use crate::input;
+use combo::*;
+use difficulty::*;
+use flashlight::*;
+use gamemod::*;
+use grade::*;
+use health::*;
 use hitobject::{HitState, HitSuccess, UpdateResult};
 use input::{InputSnapshot, InputUpdate};
+use results::*;
+use score::*;
 use std::mem::discriminant;
 
 use crate::{
@@ -26,14 +42,22 @@ use std::{
 
 pub struct Game {}
 impl Game {
-   pub fn start() {
+   pub fn start(mods: OsruGameModsActive) {
       let audio = true;
+      let playback_rate = mods.playback_rate();
 
       const _MAGIC: &str = "assets/beatmap/magic/Shihori - Magic Girl !! (Frostmourne) [Lunatic].osu";
       const _KOI: &str = "assets/beatmap/koi/KOTOKO - Koi Kou Enishi (Crystal) [Hard].osu";
       const _FANTASTIC: &str =
          "assets/beatmap/fantastic/Tamura Yukari - Fantastic future (TV Size) (Flask) [Hard].osu";
-      let (audio_filename, background_filename, mut b) = Game::start_beatmap(OsruGameMode::Standard, _MAGIC);
+      let (audio_filename, background_filename, mut b) =
+         match Game::start_beatmap(OsruGameMode::Standard, _MAGIC) {
+            Ok(result) => result,
+            Err(error) => {
+               println!("Failed to load beatmap: {}", error);
+               return;
+            }
+         };
       let background_filename = {
          if let Some(filename) = background_filename {
             filename
@@ -43,6 +67,11 @@ impl Game {
       };
 
       println!("{}", b.hitobjects.len());
+      let difficulty = calculate_difficulty(&b, &mods);
+      println!(
+         "Stars: {:.2} (aim {:.2}, speed {:.2})",
+         difficulty.star_rating, difficulty.aim_rating, difficulty.speed_rating
+      );
 
       // start audio
       let (tx, rx) = mpsc::channel();
@@ -51,6 +80,7 @@ impl Game {
       let t = std::thread::spawn(move || {
          if audio {
             let mut audio_manager = audio::AudioManager::new();
+            audio_manager.set_playback_rate(playback_rate);
             audio_manager.add_source(&audio_filename);
             ty.send(AudioMessage::Ready).unwrap();
             audio_manager.wait(rx);
@@ -101,6 +131,48 @@ impl Game {
       texture_manager.load(TextureName::HitCircle, "assets/skin/hitcircle.png");
       texture_manager.load(TextureName::Background, &background_filename);
       texture_manager.load(TextureName::ApproachCircle, "assets/skin/approachcircle.png");
+      texture_manager.load(TextureName::SliderBall, "assets/skin/sliderb0.png");
+      texture_manager.load(TextureName::ReverseArrow, "assets/skin/reversearrow.png");
+      texture_manager
+         .create_circle(TextureName::SliderBodyCircle, hitobject::slider::SLIDER_BODY_CIRCLE_RADIUS);
+      texture_manager.create_render_target(
+         TextureName::SliderBodyBuffer,
+         &PixRect::new_from_sdl2_rect(canvas.viewport()),
+      );
+      texture_manager.load(TextureName::SliderFollowCircle, "assets/skin/sliderfollowcircle.png");
+      texture_manager.load(TextureName::SliderScorePoint, "assets/skin/sliderscorepoint.png");
+      texture_manager.load(TextureName::SpinnerCircle, "assets/skin/spinner-circle.png");
+      texture_manager.load(TextureName::SpinnerApproachCircle, "assets/skin/spinner-approachcircle.png");
+      texture_manager.load(TextureName::ScoreDot, "assets/skin/score-dot.png");
+      texture_manager.load(TextureName::ScorePercent, "assets/skin/score-percent.png");
+      texture_manager.load(TextureName::Hit300, "assets/skin/hit300.png");
+      texture_manager.load(TextureName::Hit100, "assets/skin/hit100.png");
+      texture_manager.load(TextureName::Hit50, "assets/skin/hit50.png");
+      texture_manager.load(TextureName::Hit0, "assets/skin/hit0.png");
+      texture_manager.load(TextureName::RankingXH, "assets/skin/ranking-XH.png");
+      texture_manager.load(TextureName::RankingX, "assets/skin/ranking-X.png");
+      texture_manager.load(TextureName::RankingSH, "assets/skin/ranking-SH.png");
+      texture_manager.load(TextureName::RankingS, "assets/skin/ranking-S.png");
+      texture_manager.load(TextureName::RankingA, "assets/skin/ranking-A.png");
+      texture_manager.load(TextureName::RankingB, "assets/skin/ranking-B.png");
+      texture_manager.load(TextureName::RankingC, "assets/skin/ranking-C.png");
+      texture_manager.load(TextureName::RankingD, "assets/skin/ranking-D.png");
+      texture_manager.create_mask(
+         TextureName::FlashlightMask,
+         FLASHLIGHT_MASK_RADIUS,
+         FLASHLIGHT_MASK_EDGE,
+         false,
+      );
+      texture_manager.create_mask(
+         TextureName::FlashlightDim,
+         FLASHLIGHT_MASK_RADIUS,
+         FLASHLIGHT_MASK_EDGE,
+         true,
+      );
+      for digit in 0..10 {
+         let filename = format!("assets/skin/default-{}.png", digit);
+         texture_manager.load(TextureName::default_digit(digit), &filename);
+      }
 
       //input
       let event_subsys = sdl_context.event().unwrap();
@@ -116,9 +188,14 @@ impl Game {
       {
          canvas.set_draw_color(pixels::Color::RGBA(0, 0, 0, 255));
          canvas.clear();
-         display_background_image(&mut canvas, &mut background_texture.borrow_mut(), Letterboxing::Deny);
+         display_background_image(
+            &mut canvas,
+            &mut background_texture.borrow_mut(),
+            Letterboxing::Deny,
+            BACKGROUND_DIM,
+         );
       }
-      b.prepare(&viewport_size);
+      b.prepare(&viewport_size, &mods);
 
       let mut run = true;
       let mut num_frames: u64 = 0;
@@ -134,7 +211,9 @@ impl Game {
 
       // start game
       thread::sleep(Duration::from_nanos(1));
-      tx.send(AudioMessage::Play(0, BEATMAP_TIMING_OFFSET)).unwrap();
+      // the offset is in gameplay time, the audio thread sleeps in real time
+      tx.send(AudioMessage::Play(0, BEATMAP_TIMING_OFFSET.div_f64(playback_rate))).unwrap();
+      input_manager.set_playback_rate(playback_rate);
       input_manager.start_timer();
 
       // main loop
@@ -142,9 +221,22 @@ impl Game {
          let frame_start = Instant::now();
 
          b.full_update(&mut input_manager);
-         display_background_image(&mut canvas, &mut background_texture.borrow_mut(), Letterboxing::Deny);
+         display_background_image(
+            &mut canvas,
+            &mut background_texture.borrow_mut(),
+            Letterboxing::Deny,
+            b.background_dim(*input_manager.curr_snapshot().time()),
+         );
          b.draw(&mut canvas, &mut texture_manager, &mut input_manager);
-         run = run && !b.is_done();
+         if mods.is_enabled(OsruGameModName::FlashLight) {
+            let snapshot = input_manager.curr_snapshot();
+            draw_flashlight(&mut canvas, texture_manager, &b, snapshot.mouse_position(), *snapshot.time());
+         }
+         draw_health_bar(&mut canvas, b.health());
+         draw_score(&mut canvas, texture_manager, b.score());
+         draw_combo(&mut canvas, texture_manager, b.combo());
+         draw_accuracy(&mut canvas, texture_manager, b.score().hit_counts(), &b.mods);
+         run = run && !b.is_done() && !b.has_failed();
 
          input_manager.poll_all();
 
@@ -166,7 +258,7 @@ impl Game {
 
          run = run && input_manager.is_running();
          if !run {
-            if !input_manager.is_running() {
+            if !input_manager.is_running() || b.has_failed() {
                tx.send(AudioMessage::Stop).unwrap_or(());
             } else {
                tx.send(AudioMessage::Done).unwrap_or(());
@@ -183,40 +275,55 @@ impl Game {
             thread::yield_now();
          }
       }
-      let total_time = input_manager.reference_time().elapsed_now().as_secs_f64();
+      if b.has_failed() {
+         println!("Failed");
+      }
+      let total_time = input_manager.reference_time().elapsed_now().as_secs_f64() / playback_rate;
       println!("fps: avg {}", num_frames as f64 / total_time);
       {
-         use hitobject::HitState::*;
-         use hitobject::HitSuccess::*;
-         let mut num_great = 0;
-         let mut num_good = 0;
-         let mut num_meh = 0;
-         let mut num_miss = 0;
-         let mut num_unknown = 0;
-         for hitobj in b.hitobjects {
-            if let DoneDrawing(v) = hitobj.hit_state() {
-               match v {
-                  Great => num_great += 1,
-                  Good => num_good += 1,
-                  Meh => num_meh += 1,
-                  Miss => num_miss += 1,
-               }
-            } else {
-               num_unknown += 1;
-            }
-         }
+         let hit_counts = b.score().hit_counts();
          println!(
-            "Great: {}, Good: {}, Meh: {}, Miss: {}, Unknown: {}",
-            num_great, num_good, num_meh, num_miss, num_unknown
+            "Great: {}, Good: {}, Meh: {}, Miss: {}",
+            hit_counts.great, hit_counts.good, hit_counts.meh, hit_counts.miss
          );
+         println!("Accuracy: {:.2}%, Grade: {:?}", accuracy(hit_counts) * 100.0, grade(hit_counts, &b.mods));
+         println!("Score ({:?}): {}", b.score().mode(), b.score().value());
+         println!("Max combo: {}/{}", b.combo().max(), b.combo().max_possible());
+         let performance = calculate_pp(&difficulty, hit_counts, b.combo().max(), &mods);
+         println!("pp: {:.2}", performance.total);
+         if let Some(combo_result) = b.combo_result() {
+            println!("{:?}", combo_result);
+         }
          println!("Capacity: {}", input_manager.capacity());
       }
       t.join().unwrap();
+
+      // results screen, until escape is pressed
+      while input_manager.is_running() {
+         let frame_start = Instant::now();
+         display_background_image(
+            &mut canvas,
+            &mut background_texture.borrow_mut(),
+            Letterboxing::Deny,
+            BACKGROUND_DIM,
+         );
+         draw_results(&mut canvas, texture_manager, &b);
+         canvas.present();
+         input_manager.clear();
+         while LIMIT_FPS && frame_start.elapsed() < TIME_PER_FRAME {
+            thread::yield_now();
+         }
+      }
    }
 
-   pub fn start_beatmap(mode: OsruGameMode, filename: &str) -> (String, Option<String>, Beatmap) {
+   pub fn start_beatmap(
+      mode: OsruGameMode, filename: &str,
+   ) -> Result<(String, Option<String>, Beatmap), BeatmapError> {
       use beatmap::settings::{BeatmapSettingName::*, BeatmapSettings};
-      let b = beatmap::Beatmap::load(filename);
+      let b = beatmap::Beatmap::load(filename)?;
+      for warning in b.warnings.iter() {
+         println!("Warning: {}", warning);
+      }
 
       let audio_filename = b.settings.get(&AudioFilename).unwrap().parse_as_str().to_string();
       let parent_dir = match path::Path::new(filename).parent() {
@@ -241,6 +348,6 @@ impl Game {
       };
       println!("{:?} {:?} {:?}", parent_dir, audio_filename, background_filename);
 
-      (audio_filename, background_filename, b)
+      Ok((audio_filename, background_filename, b))
    }
 }
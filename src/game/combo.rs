@@ -0,0 +1,146 @@
+use crate::beatmap::hitobject::{HitSuccess, Judgement};
+use crate::global::pixel::*;
+use crate::global::*;
+
+use sdl2::render::WindowCanvas;
+
+pub const COMBO_POSITION_X: f32 = 0.08;
+pub const COMBO_POSITION_Y: f32 = 0.94;
+pub const COMBO_SCALING: f32 = 0.8;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ComboResult {
+   /// The combo was broken by a miss or a slider break
+   Broken,
+   /// Nothing was missed, though slider ends may have been dropped
+   FullCombo,
+   /// The highest possible combo was reached
+   PerfectCombo,
+}
+
+/// Counts hits and slider ticks in a row, fed every judgement in order
+#[derive(Debug, Clone, Default)]
+pub struct Combo {
+   current: u32,
+   max: u32,
+   max_possible: u32,
+   broken: bool,
+}
+impl Combo {
+   /// `perfect_play` is every judgement of the map when played perfectly
+   pub fn new(perfect_play: &[Judgement]) -> Combo {
+      let mut perfect = Combo::default();
+      for judgement in perfect_play {
+         perfect.judge(judgement);
+      }
+      Combo { max_possible: perfect.max, ..Combo::default() }
+   }
+
+   pub fn judge(&mut self, judgement: &Judgement) {
+      match judgement {
+         Judgement::Object(HitSuccess::Miss)
+         | Judgement::Slider(HitSuccess::Miss)
+         | Judgement::SliderBreak => {
+            self.current = 0;
+            self.broken = true;
+         }
+         Judgement::Object(_) | Judgement::SliderTick => self.current += 1,
+         Judgement::Slider(_) | Judgement::SliderEndMiss | Judgement::SpinnerBonus => (),
+      }
+      self.max = self.max.max(self.current);
+   }
+
+   pub fn current(&self) -> u32 {
+      self.current
+   }
+
+   pub fn max(&self) -> u32 {
+      self.max
+   }
+
+   /// Combo of a perfect play
+   pub fn max_possible(&self) -> u32 {
+      self.max_possible
+   }
+
+   pub fn result(&self) -> ComboResult {
+      if self.broken {
+         ComboResult::Broken
+      } else if self.max == self.max_possible {
+         ComboResult::PerfectCombo
+      } else {
+         ComboResult::FullCombo
+      }
+   }
+}
+
+pub fn draw_combo(canvas: &mut WindowCanvas, texture_manager: &TextureManager, combo: &Combo) {
+   if combo.current() == 0 {
+      return;
+   }
+   let viewport = PixRect::new_from_sdl2_rect(canvas.viewport());
+   let position = Pix2D::new(viewport.width() * COMBO_POSITION_X, viewport.height() * COMBO_POSITION_Y);
+   draw_number(
+      canvas,
+      texture_manager,
+      combo.current().into(),
+      TextureName::default_digit,
+      &position,
+      ScalingFactor(COMBO_SCALING),
+      Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: u8::MAX },
+   );
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn test_combo() {
+      use HitSuccess::*;
+      use Judgement::*;
+      let perfect = [Object(Great), Object(Great), Object(Great)];
+      let mut combo = Combo::new(&perfect);
+      assert_eq!(3, combo.max_possible());
+      assert_eq!(0, combo.max());
+
+      for judgement in [Object(Great), Object(Good), Object(Meh)].iter() {
+         combo.judge(judgement);
+      }
+      assert_eq!(ComboResult::PerfectCombo, combo.result());
+
+      let mut combo = Combo::new(&perfect);
+      for judgement in [Object(Great), Object(Miss), Object(Great)].iter() {
+         combo.judge(judgement);
+      }
+      assert_eq!(ComboResult::Broken, combo.result());
+      assert_eq!(1, combo.max());
+      assert_eq!(1, combo.current());
+
+      println!("Testing sliders");
+      // a circle, then a slider with one tick and a repeat: head, tick, repeat, tick, end
+      let perfect =
+         [Object(Great), SliderTick, SliderTick, SliderTick, SliderTick, SliderTick, Slider(Great)];
+      let mut combo = Combo::new(&perfect);
+      assert_eq!(6, combo.max_possible());
+      for judgement in perfect.iter() {
+         combo.judge(judgement);
+      }
+      assert_eq!(6, combo.max());
+      assert_eq!(ComboResult::PerfectCombo, combo.result());
+
+      let mut combo = Combo::new(&perfect);
+      for judgement in perfect[..5].iter().chain([SliderEndMiss, Slider(Good)].iter()) {
+         combo.judge(judgement);
+      }
+      assert_eq!(5, combo.max());
+      assert_eq!(ComboResult::FullCombo, combo.result());
+
+      let mut combo = Combo::new(&perfect);
+      for judgement in perfect[..2].iter().chain([SliderBreak, SliderTick, SliderTick, Slider(Good)].iter()) {
+         combo.judge(judgement);
+      }
+      assert_eq!(2, combo.max());
+      assert_eq!(ComboResult::Broken, combo.result());
+   }
+}
@@ -0,0 +1,81 @@
+use crate::beatmap::Beatmap;
+use crate::global::pixel::*;
+use crate::global::*;
+
+use sdl2::{pixels::Color, rect::Rect, render::WindowCanvas};
+use std::time::Duration;
+
+// size in pixels of the generated mask texture, the hole is scaled to the lit radius when drawn
+pub const FLASHLIGHT_MASK_RADIUS: u32 = 256;
+pub const FLASHLIGHT_MASK_EDGE: u32 = 64;
+// lit radius in osu!pixels before combo shrinks it
+pub const FLASHLIGHT_BASE_RADIUS: f32 = 180.0;
+// (combo reached, radius scaling)
+pub const FLASHLIGHT_COMBO_STEPS: [(u32, f32); 2] = [(200, 0.75), (100, 0.875)];
+// opacity of the extra darkening inside the lit area while a slider is held
+pub const FLASHLIGHT_SLIDER_DIM: f32 = 0.8;
+
+/// Lit radius in osu!pixels for the given combo
+pub fn flashlight_radius(combo: u32) -> Pix {
+   let scaling = FLASHLIGHT_COMBO_STEPS
+      .iter()
+      .find(|(min_combo, _)| combo >= *min_combo)
+      .map_or(1.0, |(_, scaling)| *scaling);
+   Pix::osru_pix(FLASHLIGHT_BASE_RADIUS * scaling)
+}
+
+/// Blacks out everything but a circle around the cursor, fading away during breaks
+pub fn draw_flashlight(
+   canvas: &mut WindowCanvas, texture_manager: &mut TextureManager, beatmap: &Beatmap, cursor: &Pix2D,
+   time: Duration,
+) {
+   let opacity = 1.0 - beatmap.break_fade(time);
+   if opacity <= 0.0 {
+      return;
+   }
+   let alpha = (opacity * u8::MAX as f32).round() as u8;
+   let radius = beatmap.playfield.screen_length(&flashlight_radius(beatmap.combo().current())).get_round();
+   let viewport = PixRect::new_from_sdl2_rect(canvas.viewport());
+   let (width, height) = (viewport.width().get_round(), viewport.height().get_round());
+   let (x, y) = (cursor.x().get_round(), cursor.y().get_round());
+
+   let mask = texture_manager.get(TextureName::FlashlightMask);
+   let mut mask = mask.borrow_mut();
+   mask.set_alpha_mod(alpha);
+   let lit_area = Rect::new(x - radius, y - radius, radius as u32 * 2, radius as u32 * 2);
+   canvas.copy(&mask, None, lit_area).unwrap();
+
+   canvas.set_draw_color(Color::RGBA(0, 0, 0, alpha));
+   let surrounding = [
+      (0, 0, width, y - radius),
+      (0, y + radius, width, height - (y + radius)),
+      (0, y - radius, x - radius, radius * 2),
+      (x + radius, y - radius, width - (x + radius), radius * 2),
+   ];
+   for (left, top, w, h) in surrounding.iter() {
+      if *w > 0 && *h > 0 {
+         canvas.fill_rect(Rect::new(*left, *top, *w as u32, *h as u32)).unwrap();
+      }
+   }
+
+   if beatmap.is_slider_held() {
+      // the inverted mask darkens the lit area and fades out where the mask fades in
+      let dim = texture_manager.get(TextureName::FlashlightDim);
+      let mut dim = dim.borrow_mut();
+      dim.set_alpha_mod((alpha as f32 * FLASHLIGHT_SLIDER_DIM) as u8);
+      canvas.copy(&dim, None, lit_area).unwrap();
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn test_flashlight_radius() {
+      assert_eq!(FLASHLIGHT_BASE_RADIUS, flashlight_radius(0).get());
+      assert_eq!(FLASHLIGHT_BASE_RADIUS, flashlight_radius(99).get());
+      assert_eq!(FLASHLIGHT_BASE_RADIUS * 0.875, flashlight_radius(100).get());
+      assert_eq!(FLASHLIGHT_BASE_RADIUS * 0.75, flashlight_radius(250).get());
+   }
+}
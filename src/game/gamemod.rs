@@ -1,122 +1,293 @@
-
-#[derive(Debug, Clone)]
-pub struct OsruGameModsActive {
-  mods: HashSet<OsruGameMod>,
-}
-impl OsruGameModsActive {
-  pub fn new() -> OsruGameModsActive {
-    OsruGameModsActive { mods: HashSet::new() }
-  }
-
-  pub fn enable_game_mod(&mut self, new_mod: OsruGameModName) {
-    let mut to_remove = vec![];
-    let new_mod = OsruGameMod::new(new_mod);
-    {
-      for m in self.mods.iter() {
-        if new_mod.eq(m) {
-          return;
-        }
-        for exclude in m.exclusive() {
-          if new_mod.name().eq(exclude) {
-            to_remove.push(OsruGameMod::new(*exclude));
-          }
-        }
-      }
-    }
-
-    for m in to_remove.iter() {
-      self.mods.remove(m);
-    }
-    self.mods.insert(new_mod);
-  }
-
-  pub fn disable_game_mod(&mut self, mod_to_disable: OsruGameModName) {
-    let mod_to_disable = OsruGameMod::new(mod_to_disable);
-    self.mods.remove(&mod_to_disable);
-  }
-}
-
-#[derive(Debug, Clone)]
-pub struct OsruGameMod {
-  game_mod_name: OsruGameModName,
-  exclusive: Vec<OsruGameModName>,
-
-  ar_multiplier: f64,
-  od_multiplier: f64,
-  cs_multiplier: f64,
-}
-impl OsruGameMod {
-  pub fn new(name: OsruGameModName) -> OsruGameMod {
-    use OsruGameModName::*;
-    let mut result = OsruGameMod::default();
-    result.game_mod_name = name;
-    match name {
-      Easy => {
-        result.exclusive.push(HardRock);
-        result.ar_multiplier = 0.5;
-        result.od_multiplier = 0.5;
-      }
-      HardRock => {
-        result.exclusive.push(Easy);
-        result.ar_multiplier = 1.4;
-        result.od_multiplier = 1.4;
-      }
-      _ => (),
-    }
-    result
-  }
-
-  // TODO: other mods
-  pub fn exclusive<'a>(&'a self) -> slice::Iter<'a, OsruGameModName> {
-    self.exclusive.iter()
-  }
-
-  pub fn name(&self) -> OsruGameModName {
-    self.game_mod_name
-  }
-}
-impl Default for OsruGameMod {
-  fn default() -> Self {
-    OsruGameMod {
-      game_mod_name: OsruGameModName::None,
-      exclusive: vec![],
-      ar_multiplier: 1.0,
-      od_multiplier: 1.0,
-      cs_multiplier: 1.0,
-    }
-  }
-}
-impl hash::Hash for OsruGameMod {
-  fn hash<H: hash::Hasher>(&self, state: &mut H) {
-    self.game_mod_name.hash(state);
-  }
-}
-impl cmp::PartialEq for OsruGameMod {
-  fn eq(&self, other: &OsruGameMod) -> bool {
-    self.game_mod_name == other.game_mod_name
-  }
-}
-impl cmp::Eq for OsruGameMod {}
-
-#[derive(Debug, Clone, Hash, Eq, PartialEq, IntoEnumIterator)]
-pub enum OsruGameModName {
-  None,
-  Easy,
-  HardRock,
-  DoubleTime,
-  HalfTime,
-  NoFail,
-  SuddenDeath,
-  Perfect,
-  Hidden,
-  FlashLight,
-  //Scoring
-  ScoreOsru,
-  ScoreV1,
-  ScoreV2,
-  //Special
-  Relax,
-  AutoPilot,
-  SpunOut,
-  Auto,
-}
+use crate::beatmap::hitobject::{HitSuccess, Judgement};
+use crate::global::playfield::PlayfieldTransform;
+use enum_iterator::IntoEnumIterator;
+
+use std::{cmp, collections::HashSet, hash, slice};
+
+#[derive(Debug, Clone)]
+pub struct OsruGameModsActive {
+   mods: HashSet<OsruGameMod>,
+}
+impl OsruGameModsActive {
+   pub fn new() -> OsruGameModsActive {
+      OsruGameModsActive { mods: HashSet::new() }
+   }
+
+   pub fn enable_game_mod(&mut self, new_mod: OsruGameModName) {
+      let mut to_remove = vec![];
+      let new_mod = OsruGameMod::new(new_mod);
+      {
+         for m in self.mods.iter() {
+            if new_mod.eq(m) {
+               return;
+            }
+            for exclude in m.exclusive() {
+               if new_mod.name().eq(exclude) {
+                  to_remove.push(OsruGameMod::new(m.name()));
+               }
+            }
+         }
+      }
+
+      for m in to_remove.iter() {
+         self.mods.remove(m);
+      }
+      self.mods.insert(new_mod);
+   }
+
+   pub fn disable_game_mod(&mut self, mod_to_disable: OsruGameModName) {
+      let mod_to_disable = OsruGameMod::new(mod_to_disable);
+      self.mods.remove(&mod_to_disable);
+   }
+
+   pub fn is_enabled(&self, name: OsruGameModName) -> bool {
+      self.mods.iter().any(|m| m.name() == name)
+   }
+
+   /// Enables mods from acronyms like "HD" or "HDHR", returning the ones that weren't recognised
+   pub fn enable_from_acronyms(&mut self, acronyms: &str) -> Vec<String> {
+      let acronyms = acronyms.to_uppercase();
+      let mut unknown = vec![];
+      for i in (0..acronyms.len()).step_by(2) {
+         let acronym = acronyms.get(i..cmp::min(i + 2, acronyms.len())).unwrap_or_default();
+         match OsruGameModName::from_acronym(acronym) {
+            Some(name) => self.enable_game_mod(name),
+            Option::None => unknown.push(acronym.to_string()),
+         }
+      }
+      unknown
+   }
+
+   pub fn iter(&self) -> impl Iterator<Item = &OsruGameMod> {
+      self.mods.iter()
+   }
+
+   pub fn ar_multiplier(&self) -> f64 {
+      self.mods.iter().map(|m| m.ar_multiplier).product()
+   }
+   pub fn od_multiplier(&self) -> f64 {
+      self.mods.iter().map(|m| m.od_multiplier).product()
+   }
+   pub fn cs_multiplier(&self) -> f64 {
+      self.mods.iter().map(|m| m.cs_multiplier).product()
+   }
+   pub fn hp_multiplier(&self) -> f64 {
+      self.mods.iter().map(|m| m.hp_multiplier).product()
+   }
+   /// How fast the song and the gameplay clock run compared to normal
+   pub fn playback_rate(&self) -> f64 {
+      self.mods.iter().map(|m| m.playback_rate).product()
+   }
+   pub fn score_multiplier(&self) -> f64 {
+      self.mods.iter().map(|m| m.score_multiplier).product()
+   }
+
+   /// Whether the judgement fails the play, under SuddenDeath any miss or slider break does and under
+   /// Perfect anything short of Great
+   pub fn fails_on(&self, judgement: &Judgement) -> bool {
+      use OsruGameModName::*;
+      match judgement {
+         Judgement::Object(HitSuccess::Great)
+         | Judgement::Slider(HitSuccess::Great)
+         | Judgement::SliderTick
+         | Judgement::SliderEndMiss
+         | Judgement::SpinnerBonus => false,
+         Judgement::Object(HitSuccess::Miss)
+         | Judgement::Slider(HitSuccess::Miss)
+         | Judgement::SliderBreak => self.is_enabled(SuddenDeath) || self.is_enabled(Perfect),
+         Judgement::Object(_) | Judgement::Slider(_) => self.is_enabled(Perfect),
+      }
+   }
+
+   /// Every position transform of the active mods, in the order they should be applied
+   pub fn playfield_transforms(&self) -> Vec<PlayfieldTransform> {
+      let mut mods: Vec<&OsruGameMod> = self.mods.iter().collect();
+      mods.sort_by_key(|m| m.name() as usize);
+      mods.iter().flat_map(|m| m.transforms.iter().copied()).collect()
+   }
+}
+impl Default for OsruGameModsActive {
+   fn default() -> Self {
+      OsruGameModsActive::new()
+   }
+}
+
+#[derive(Debug, Clone)]
+pub struct OsruGameMod {
+   game_mod_name: OsruGameModName,
+   exclusive: Vec<OsruGameModName>,
+
+   ar_multiplier: f64,
+   od_multiplier: f64,
+   cs_multiplier: f64,
+   hp_multiplier: f64,
+   playback_rate: f64,
+   score_multiplier: f64,
+   transforms: Vec<PlayfieldTransform>,
+}
+impl OsruGameMod {
+   pub fn new(name: OsruGameModName) -> OsruGameMod {
+      use OsruGameModName::*;
+      let mut result = OsruGameMod::default();
+      result.game_mod_name = name;
+      match name {
+         Easy => {
+            result.exclusive.push(HardRock);
+            result.ar_multiplier = 0.5;
+            result.od_multiplier = 0.5;
+            result.cs_multiplier = 0.5;
+            result.hp_multiplier = 0.5;
+            result.score_multiplier = 0.5;
+         }
+         HardRock => {
+            result.exclusive.push(Easy);
+            result.ar_multiplier = 1.4;
+            result.od_multiplier = 1.4;
+            result.cs_multiplier = 1.3;
+            result.hp_multiplier = 1.4;
+            result.score_multiplier = 1.06;
+            result.transforms.push(PlayfieldTransform::FlipVertical);
+         }
+         DoubleTime => {
+            result.exclusive.push(HalfTime);
+            result.playback_rate = 1.5;
+            result.score_multiplier = 1.12;
+         }
+         HalfTime => {
+            result.exclusive.push(DoubleTime);
+            result.playback_rate = 0.75;
+            result.score_multiplier = 0.3;
+         }
+         NoFail => {
+            result.exclusive.extend(&[SuddenDeath, Perfect]);
+            result.score_multiplier = 0.5;
+         }
+         SuddenDeath => result.exclusive.extend(&[NoFail, Perfect]),
+         Perfect => result.exclusive.extend(&[NoFail, SuddenDeath]),
+         Hidden => result.score_multiplier = 1.06,
+         FlashLight => result.score_multiplier = 1.12,
+         ScoreOsru => result.exclusive.extend(&[ScoreV1, ScoreV2]),
+         ScoreV1 => result.exclusive.extend(&[ScoreOsru, ScoreV2]),
+         ScoreV2 => result.exclusive.extend(&[ScoreOsru, ScoreV1]),
+         Relax | AutoPilot => result.score_multiplier = 0.0,
+         SpunOut => result.score_multiplier = 0.9,
+         _ => (),
+      }
+      result
+   }
+
+   // TODO: other mods
+   pub fn exclusive<'a>(&'a self) -> slice::Iter<'a, OsruGameModName> {
+      self.exclusive.iter()
+   }
+
+   pub fn name(&self) -> OsruGameModName {
+      self.game_mod_name
+   }
+}
+impl Default for OsruGameMod {
+   fn default() -> Self {
+      OsruGameMod {
+         game_mod_name: OsruGameModName::None,
+         exclusive: vec![],
+         ar_multiplier: 1.0,
+         od_multiplier: 1.0,
+         cs_multiplier: 1.0,
+         hp_multiplier: 1.0,
+         playback_rate: 1.0,
+         score_multiplier: 1.0,
+         transforms: vec![],
+      }
+   }
+}
+impl hash::Hash for OsruGameMod {
+   fn hash<H: hash::Hasher>(&self, state: &mut H) {
+      self.game_mod_name.hash(state);
+   }
+}
+impl cmp::PartialEq for OsruGameMod {
+   fn eq(&self, other: &OsruGameMod) -> bool {
+      self.game_mod_name == other.game_mod_name
+   }
+}
+impl cmp::Eq for OsruGameMod {}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, IntoEnumIterator)]
+pub enum OsruGameModName {
+   None,
+   Easy,
+   HardRock,
+   DoubleTime,
+   HalfTime,
+   NoFail,
+   SuddenDeath,
+   Perfect,
+   Hidden,
+   FlashLight,
+   //Scoring
+   ScoreOsru,
+   ScoreV1,
+   ScoreV2,
+   //Special
+   Relax,
+   AutoPilot,
+   SpunOut,
+   Auto,
+}
+impl OsruGameModName {
+   pub fn from_acronym(acronym: &str) -> Option<OsruGameModName> {
+      use OsruGameModName::*;
+      match acronym {
+         "EZ" => Some(Easy),
+         "HR" => Some(HardRock),
+         "DT" => Some(DoubleTime),
+         "HT" => Some(HalfTime),
+         "NF" => Some(NoFail),
+         "SD" => Some(SuddenDeath),
+         "PF" => Some(Perfect),
+         "HD" => Some(Hidden),
+         "FL" => Some(FlashLight),
+         "RX" => Some(Relax),
+         "AP" => Some(AutoPilot),
+         "SO" => Some(SpunOut),
+         "AT" => Some(Auto),
+         _ => Option::None,
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn test_mods() {
+      let mut mods = OsruGameModsActive::new();
+      assert!(mods.enable_from_acronyms("ezhdxx").contains(&String::from("XX")));
+      assert!(mods.is_enabled(OsruGameModName::Easy) && mods.is_enabled(OsruGameModName::Hidden));
+      assert_eq!(0.5, mods.ar_multiplier());
+
+      println!("Testing exclusive mods");
+      mods.enable_game_mod(OsruGameModName::HardRock);
+      assert!(!mods.is_enabled(OsruGameModName::Easy));
+      assert_eq!(1.3, mods.cs_multiplier());
+
+      println!("Testing playback rate");
+      assert_eq!(1.0, mods.playback_rate());
+      mods.enable_game_mod(OsruGameModName::DoubleTime);
+      assert_eq!(1.5, mods.playback_rate());
+      mods.enable_game_mod(OsruGameModName::HalfTime);
+      assert!(!mods.is_enabled(OsruGameModName::DoubleTime));
+      assert_eq!(0.75, mods.playback_rate());
+
+      println!("Testing fail conditions");
+      assert!(!mods.fails_on(&Judgement::SliderBreak));
+      mods.enable_game_mod(OsruGameModName::SuddenDeath);
+      assert!(mods.fails_on(&Judgement::SliderBreak));
+      assert!(!mods.fails_on(&Judgement::Object(HitSuccess::Good)));
+      assert!(!mods.fails_on(&Judgement::SliderEndMiss));
+      mods.enable_game_mod(OsruGameModName::Perfect);
+      assert!(mods.fails_on(&Judgement::Object(HitSuccess::Good)));
+      assert!(!mods.fails_on(&Judgement::Object(HitSuccess::Great)));
+   }
+}
@@ -0,0 +1,158 @@
+use crate::beatmap::hitobject::HitSuccess;
+use crate::game::gamemod::*;
+use crate::global::pixel::*;
+use crate::global::*;
+
+use sdl2::render::WindowCanvas;
+
+pub const ACCURACY_POSITION_X: f32 = 0.88;
+pub const ACCURACY_POSITION_Y: f32 = 0.09;
+pub const ACCURACY_SCALING: f32 = 0.4;
+pub const GRADE_POSITION_X: f32 = 0.78;
+pub const GRADE_POSITION_Y: f32 = 0.09;
+pub const GRADE_SCALING: f32 = 0.15;
+
+/// How many objects were judged as each `HitSuccess`
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct HitCounts {
+   pub great: u32,
+   pub good: u32,
+   pub meh: u32,
+   pub miss: u32,
+}
+impl HitCounts {
+   pub fn add(&mut self, hit_success: HitSuccess) {
+      use HitSuccess::*;
+      match hit_success {
+         Great => self.great += 1,
+         Good => self.good += 1,
+         Meh => self.meh += 1,
+         Miss => self.miss += 1,
+      }
+   }
+
+   pub fn total(&self) -> u32 {
+      self.great + self.good + self.meh + self.miss
+   }
+}
+
+/// Fraction from 0.0 to 1.0 of the 300/100/50 weighted hits, 1.0 when nothing has been judged
+pub fn accuracy(hit_counts: &HitCounts) -> f64 {
+   if hit_counts.total() == 0 {
+      return 1.0;
+   }
+   let points = hit_counts.great * 300 + hit_counts.good * 100 + hit_counts.meh * 50;
+   points as f64 / (hit_counts.total() * 300) as f64
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Grade {
+   /// SS with Hidden or Flashlight
+   SilverSS,
+   SS,
+   /// S with Hidden or Flashlight
+   SilverS,
+   S,
+   A,
+   B,
+   C,
+   D,
+}
+impl Grade {
+   pub fn texture_name(&self) -> TextureName {
+      use Grade::*;
+      match self {
+         SilverSS => TextureName::RankingXH,
+         SS => TextureName::RankingX,
+         SilverS => TextureName::RankingSH,
+         S => TextureName::RankingS,
+         A => TextureName::RankingA,
+         B => TextureName::RankingB,
+         C => TextureName::RankingC,
+         D => TextureName::RankingD,
+      }
+   }
+}
+
+/// Grades by the share of Greats: S needs over 90% with under 1% Mehs and no misses, A over 80% without
+/// misses or over 90%, B over 70% without misses or over 80%, C over 60%
+pub fn grade(hit_counts: &HitCounts, mods: &OsruGameModsActive) -> Grade {
+   let silver = mods.is_enabled(OsruGameModName::Hidden) || mods.is_enabled(OsruGameModName::FlashLight);
+   let total = hit_counts.total().max(1) as f64;
+   let great = hit_counts.great as f64 / total;
+   let meh = hit_counts.meh as f64 / total;
+   let no_miss = hit_counts.miss == 0;
+
+   if hit_counts.total() == 0 || hit_counts.great == hit_counts.total() {
+      if silver {
+         Grade::SilverSS
+      } else {
+         Grade::SS
+      }
+   } else if great > 0.9 && meh < 0.01 && no_miss {
+      if silver {
+         Grade::SilverS
+      } else {
+         Grade::S
+      }
+   } else if (great > 0.8 && no_miss) || great > 0.9 {
+      Grade::A
+   } else if (great > 0.7 && no_miss) || great > 0.8 {
+      Grade::B
+   } else if great > 0.6 {
+      Grade::C
+   } else {
+      Grade::D
+   }
+}
+
+pub fn draw_accuracy(
+   canvas: &mut WindowCanvas, texture_manager: &TextureManager, hit_counts: &HitCounts,
+   mods: &OsruGameModsActive,
+) {
+   let viewport = PixRect::new_from_sdl2_rect(canvas.viewport());
+   let white = Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: u8::MAX };
+   let position = Pix2D::new(viewport.width() * ACCURACY_POSITION_X, viewport.height() * ACCURACY_POSITION_Y);
+   let text = format!("{:.2}%", accuracy(hit_counts) * 100.0);
+   draw_text(canvas, texture_manager, &text, &position, ScalingFactor(ACCURACY_SCALING), white);
+
+   let position = Pix2D::new(viewport.width() * GRADE_POSITION_X, viewport.height() * GRADE_POSITION_Y);
+   draw_texture(
+      canvas,
+      texture_manager,
+      grade(hit_counts, mods).texture_name(),
+      &position,
+      ScalingFactor(GRADE_SCALING),
+   );
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use assert_approx_eq::assert_approx_eq;
+
+   #[test]
+   fn test_accuracy() {
+      let hit_counts = HitCounts { great: 90, good: 6, meh: 2, miss: 2 };
+      assert_approx_eq!((27000.0 + 600.0 + 100.0) / 30000.0, accuracy(&hit_counts));
+      assert_eq!(1.0, accuracy(&HitCounts::default()));
+   }
+
+   #[test]
+   fn test_grade() {
+      let mut mods = OsruGameModsActive::new();
+      assert_eq!(Grade::SS, grade(&HitCounts { great: 10, ..HitCounts::default() }, &mods));
+      assert_eq!(Grade::S, grade(&HitCounts { great: 95, good: 5, ..HitCounts::default() }, &mods));
+      assert_eq!(Grade::A, grade(&HitCounts { great: 95, good: 4, meh: 1, miss: 0 }, &mods));
+      assert_eq!(Grade::A, grade(&HitCounts { great: 95, miss: 5, ..HitCounts::default() }, &mods));
+      assert_eq!(Grade::B, grade(&HitCounts { great: 85, miss: 15, ..HitCounts::default() }, &mods));
+      assert_eq!(Grade::C, grade(&HitCounts { great: 65, miss: 35, ..HitCounts::default() }, &mods));
+      assert_eq!(Grade::D, grade(&HitCounts { great: 50, miss: 50, ..HitCounts::default() }, &mods));
+
+      println!("Testing silver grades");
+      mods.enable_game_mod(OsruGameModName::Hidden);
+      assert_eq!(Grade::SilverSS, grade(&HitCounts { great: 10, ..HitCounts::default() }, &mods));
+      assert_eq!(Grade::SilverS, grade(&HitCounts { great: 95, good: 5, ..HitCounts::default() }, &mods));
+      assert_eq!(Grade::A, grade(&HitCounts { great: 85, good: 15, ..HitCounts::default() }, &mods));
+   }
+}
@@ -0,0 +1,132 @@
+use crate::beatmap::hitobject::{HitSuccess, Judgement};
+use crate::global::pixel::*;
+
+use sdl2::{pixels::Color, rect::Rect, render::WindowCanvas};
+use std::time::Duration;
+
+pub const HEALTH_MAX: f64 = 1.0;
+// health drained per second is HEALTH_DRAIN_BASE + HEALTH_DRAIN_PER_HP * HPDrainRate
+pub const HEALTH_DRAIN_BASE: f64 = 0.01;
+pub const HEALTH_DRAIN_PER_HP: f64 = 0.004;
+pub const HEALTH_GAIN_GREAT: f64 = 0.05;
+pub const HEALTH_GAIN_GOOD: f64 = 0.02;
+pub const HEALTH_GAIN_MEH: f64 = 0.005;
+pub const HEALTH_GAIN_SLIDER_TICK: f64 = 0.01;
+// gains are scaled by 1 - HPDrainRate * this, so they halve at HP 10
+pub const HEALTH_GAIN_REDUCTION_PER_HP: f64 = 0.05;
+// a miss loses HEALTH_MISS_BASE + HEALTH_MISS_PER_HP * HPDrainRate, a slider break a fraction of that
+pub const HEALTH_MISS_BASE: f64 = 0.05;
+pub const HEALTH_MISS_PER_HP: f64 = 0.015;
+pub const HEALTH_SLIDER_BREAK_FRACTION: f64 = 0.25;
+
+// position and size of the bar as fractions of the screen
+pub const HEALTH_BAR_X: f32 = 0.02;
+pub const HEALTH_BAR_Y: f32 = 0.02;
+pub const HEALTH_BAR_WIDTH: f32 = 0.4;
+pub const HEALTH_BAR_HEIGHT: f32 = 0.015;
+
+/// Player health from 0.0 to `HEALTH_MAX`, drained over time and restored by hitting objects
+#[derive(Debug, Clone)]
+pub struct Health {
+   value: f64,
+   hp_drain_rate: f64,
+   last_update: Option<Duration>,
+}
+impl Health {
+   pub fn new(hp_drain_rate: f64) -> Health {
+      Health { value: HEALTH_MAX, hp_drain_rate, last_update: None }
+   }
+
+   pub fn value(&self) -> f64 {
+      self.value
+   }
+
+   pub fn is_empty(&self) -> bool {
+      self.value <= 0.0
+   }
+
+   /// Advances to `time`, draining health for the time passed if `draining`
+   pub fn update(&mut self, time: Duration, draining: bool) {
+      let elapsed = time.saturating_sub(self.last_update.unwrap_or(time));
+      self.last_update = Some(time);
+      if draining {
+         self.change(-self.drain_per_second() * elapsed.as_secs_f64());
+      }
+   }
+
+   pub fn judge(&mut self, judgement: &Judgement) {
+      use HitSuccess::*;
+      let gain_scaling = 1.0 - self.hp_drain_rate * HEALTH_GAIN_REDUCTION_PER_HP;
+      let miss_penalty = HEALTH_MISS_BASE + HEALTH_MISS_PER_HP * self.hp_drain_rate;
+      let change = match judgement {
+         Judgement::Object(hit_success) | Judgement::Slider(hit_success) => match hit_success {
+            Great => HEALTH_GAIN_GREAT * gain_scaling,
+            Good => HEALTH_GAIN_GOOD * gain_scaling,
+            Meh => HEALTH_GAIN_MEH * gain_scaling,
+            Miss => -miss_penalty,
+         },
+         Judgement::SliderTick => HEALTH_GAIN_SLIDER_TICK * gain_scaling,
+         Judgement::SliderBreak => -miss_penalty * HEALTH_SLIDER_BREAK_FRACTION,
+         Judgement::SliderEndMiss | Judgement::SpinnerBonus => 0.0,
+      };
+      self.change(change);
+   }
+
+   fn drain_per_second(&self) -> f64 {
+      HEALTH_DRAIN_BASE + HEALTH_DRAIN_PER_HP * self.hp_drain_rate
+   }
+
+   fn change(&mut self, amount: f64) {
+      self.value = (self.value + amount).clamp(0.0, HEALTH_MAX);
+   }
+}
+impl Default for Health {
+   fn default() -> Self {
+      Health::new(0.0)
+   }
+}
+
+pub fn draw_health_bar(canvas: &mut WindowCanvas, health: &Health) {
+   let viewport = PixRect::new_from_sdl2_rect(canvas.viewport());
+   let x = (viewport.width() * HEALTH_BAR_X).get_round();
+   let y = (viewport.height() * HEALTH_BAR_Y).get_round();
+   let width = (viewport.width() * HEALTH_BAR_WIDTH).get_round() as u32;
+   let height = (viewport.height() * HEALTH_BAR_HEIGHT).get_round() as u32;
+   let filled = (width as f64 * health.value() / HEALTH_MAX).round() as u32;
+
+   canvas.set_draw_color(Color::RGBA(0, 0, 0, u8::MAX / 2));
+   canvas.fill_rect(Rect::new(x, y, width, height)).unwrap();
+   if filled > 0 {
+      canvas.set_draw_color(Color::RGBA(u8::MAX, u8::MAX, u8::MAX, u8::MAX));
+      canvas.fill_rect(Rect::new(x, y, filled, height)).unwrap();
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use assert_approx_eq::assert_approx_eq;
+
+   #[test]
+   fn test_health() {
+      let mut health = Health::new(5.0);
+      health.update(Duration::from_secs(2), true);
+      assert_eq!(HEALTH_MAX, health.value());
+
+      println!("Testing drain");
+      health.update(Duration::from_secs(12), true);
+      assert_approx_eq!(0.7, health.value());
+      health.update(Duration::from_secs(20), false);
+      assert_approx_eq!(0.7, health.value());
+
+      println!("Testing judgements");
+      health.judge(&Judgement::Object(HitSuccess::Great));
+      assert_approx_eq!(0.7375, health.value());
+      health.judge(&Judgement::SliderBreak);
+      assert_approx_eq!(0.70625, health.value());
+      for _ in 0..6 {
+         health.judge(&Judgement::Object(HitSuccess::Miss));
+      }
+      assert!(health.is_empty());
+   }
+}
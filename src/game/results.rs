@@ -0,0 +1,60 @@
+use crate::beatmap::Beatmap;
+use crate::game::grade::*;
+use crate::global::pixel::*;
+use crate::global::*;
+
+use sdl2::render::WindowCanvas;
+
+// positions are fractions of the screen
+pub const RESULTS_SCORE_POSITION: (f32, f32) = (0.3, 0.15);
+pub const RESULTS_GRADE_POSITION: (f32, f32) = (0.75, 0.45);
+pub const RESULTS_HIT_COUNTS_X: (f32, f32) = (0.1, 0.25);
+pub const RESULTS_HIT_COUNTS_Y: [f32; 4] = [0.32, 0.44, 0.56, 0.68];
+pub const RESULTS_COMBO_POSITION: (f32, f32) = (0.15, 0.82);
+pub const RESULTS_ACCURACY_POSITION: (f32, f32) = (0.4, 0.82);
+pub const RESULTS_TEXT_SCALING: f32 = 0.8;
+pub const RESULTS_HIT_SCALING: f32 = 0.6;
+pub const RESULTS_GRADE_SCALING: f32 = 1.0;
+
+/// Draws the score, hit counts, max combo, accuracy and grade of a finished play
+pub fn draw_results(canvas: &mut WindowCanvas, texture_manager: &TextureManager, beatmap: &Beatmap) {
+   let viewport = PixRect::new_from_sdl2_rect(canvas.viewport());
+   let at = |(x, y): (f32, f32)| Pix2D::new(viewport.width() * x, viewport.height() * y);
+   let white = Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: u8::MAX };
+   let text_scaling = ScalingFactor(RESULTS_TEXT_SCALING);
+   let hit_counts = beatmap.score().hit_counts();
+
+   let score = beatmap.score().value().to_string();
+   draw_text(canvas, texture_manager, &score, &at(RESULTS_SCORE_POSITION), text_scaling, white);
+
+   let rows = [
+      (TextureName::Hit300, hit_counts.great),
+      (TextureName::Hit100, hit_counts.good),
+      (TextureName::Hit50, hit_counts.meh),
+      (TextureName::Hit0, hit_counts.miss),
+   ];
+   for ((texture_name, count), y) in rows.iter().zip(RESULTS_HIT_COUNTS_Y.iter()) {
+      let (texture_x, count_x) = RESULTS_HIT_COUNTS_X;
+      draw_texture(
+         canvas,
+         texture_manager,
+         *texture_name,
+         &at((texture_x, *y)),
+         ScalingFactor(RESULTS_HIT_SCALING),
+      );
+      draw_text(canvas, texture_manager, &count.to_string(), &at((count_x, *y)), text_scaling, white);
+   }
+
+   let combo = beatmap.combo().max().to_string();
+   draw_text(canvas, texture_manager, &combo, &at(RESULTS_COMBO_POSITION), text_scaling, white);
+   let accuracy = format!("{:.2}%", accuracy(hit_counts) * 100.0);
+   draw_text(canvas, texture_manager, &accuracy, &at(RESULTS_ACCURACY_POSITION), text_scaling, white);
+
+   draw_texture(
+      canvas,
+      texture_manager,
+      grade(hit_counts, &beatmap.mods).texture_name(),
+      &at(RESULTS_GRADE_POSITION),
+      ScalingFactor(RESULTS_GRADE_SCALING),
+   );
+}
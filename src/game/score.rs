@@ -0,0 +1,235 @@
+use crate::beatmap::hitobject::{HitSuccess, Judgement};
+use crate::beatmap::settings::BeatmapSettings;
+use crate::game::combo::Combo;
+use crate::game::gamemod::*;
+use crate::game::grade::*;
+use crate::global::pixel::*;
+use crate::global::*;
+
+use sdl2::render::WindowCanvas;
+use std::time::Duration;
+
+pub const SCORE_GREAT: u64 = 300;
+pub const SCORE_GOOD: u64 = 100;
+pub const SCORE_MEH: u64 = 50;
+pub const SCORE_SLIDER_TICK: u64 = 10;
+pub const SCORE_SPINNER_BONUS: u64 = 1000;
+// ScoreV1 adds hit value * combo * difficulty multiplier * mod multiplier / this
+pub const SCORE_V1_COMBO_DIVISOR: f64 = 25.0;
+pub const SCORE_MAX: f64 = 1_000_000.0;
+// ScoreV2 splits SCORE_MAX between combo and accuracy
+pub const SCORE_V2_COMBO_PORTION: f64 = 0.7;
+pub const SCORE_V2_ACCURACY_PORTION: f64 = 0.3;
+pub const SCORE_V2_ACCURACY_EXPONENT: i32 = 10;
+pub const SCORE_V2_COMBO_DIVISOR: f64 = 10.0;
+
+pub const SCORE_POSITION_X: f32 = 0.88;
+pub const SCORE_POSITION_Y: f32 = 0.04;
+pub const SCORE_SCALING: f32 = 0.6;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ScoringMode {
+   /// Hit values, weighted by accuracy only, scaled to a million
+   ScoreOsru,
+   /// Hit values boosted by combo, difficulty and mods, without an upper limit
+   ScoreV1,
+   /// A million split between combo and accuracy
+   ScoreV2,
+}
+impl ScoringMode {
+   pub fn new(mods: &OsruGameModsActive) -> ScoringMode {
+      if mods.is_enabled(OsruGameModName::ScoreOsru) {
+         ScoringMode::ScoreOsru
+      } else if mods.is_enabled(OsruGameModName::ScoreV2) {
+         ScoringMode::ScoreV2
+      } else {
+         ScoringMode::ScoreV1
+      }
+   }
+}
+
+pub fn hit_value(hit_success: HitSuccess) -> u64 {
+   use HitSuccess::*;
+   match hit_success {
+      Great => SCORE_GREAT,
+      Good => SCORE_GOOD,
+      Meh => SCORE_MEH,
+      Miss => 0,
+   }
+}
+
+/// Difficulty multiplier of ScoreV1, from the unmodded settings and how dense the map is
+pub fn difficulty_multiplier(settings: &BeatmapSettings, object_count: usize, drain_time: Duration) -> f64 {
+   let density = if drain_time.as_secs_f64() > 0.0 {
+      (object_count as f64 / drain_time.as_secs_f64() * 8.0).clamp(0.0, 16.0)
+   } else {
+      0.0
+   };
+   let difficulty =
+      settings.hp_drain_rate() + settings.circle_size().0 + settings.overall_difficulty().0 + density;
+   (difficulty / 38.0 * 5.0).round()
+}
+
+/// Running score of a play, fed every judgement in order
+#[derive(Debug, Clone)]
+pub struct Score {
+   mode: ScoringMode,
+   mod_multiplier: f64,
+   difficulty_multiplier: f64,
+   score_v1: f64,
+   /// Spinner bonus, added on top of ScoreV2's million
+   bonus: f64,
+   combo_portion: f64,
+   max_combo_portion: f64,
+   hit_counts: HitCounts,
+   total_objects: u32,
+}
+impl Score {
+   /// `perfect_play` is every judgement of the map when played perfectly, used to scale ScoreV2 and
+   /// ScoreOsru to a million
+   pub fn new(
+      mode: ScoringMode, mods: &OsruGameModsActive, difficulty_multiplier: f64, perfect_play: &[Judgement],
+   ) -> Score {
+      let mut score = Score {
+         mode,
+         mod_multiplier: mods.score_multiplier(),
+         difficulty_multiplier,
+         score_v1: 0.0,
+         bonus: 0.0,
+         combo_portion: 0.0,
+         max_combo_portion: 0.0,
+         hit_counts: HitCounts::default(),
+         total_objects: 0,
+      };
+      let mut perfect = score.clone();
+      let mut combo = Combo::new(perfect_play);
+      for judgement in perfect_play {
+         perfect.judge(judgement, &combo);
+         combo.judge(judgement);
+      }
+      score.max_combo_portion = perfect.combo_portion;
+      score.total_objects = perfect.hit_counts.total();
+      score
+   }
+
+   /// `combo` is the combo before the judgement
+   pub fn judge(&mut self, judgement: &Judgement, combo: &Combo) {
+      match judgement {
+         Judgement::Object(hit_success) | Judgement::Slider(hit_success) => {
+            let value = hit_value(*hit_success);
+            let combo_multiplier = combo.current().saturating_sub(1) as f64;
+            self.score_v1 += value as f64
+               + value as f64 * combo_multiplier * self.difficulty_multiplier * self.mod_multiplier
+                  / SCORE_V1_COMBO_DIVISOR;
+            self.combo_portion += value as f64 * (1.0 + combo.current() as f64 / SCORE_V2_COMBO_DIVISOR);
+            self.hit_counts.add(*hit_success);
+         }
+         Judgement::SliderTick => self.score_v1 += SCORE_SLIDER_TICK as f64,
+         Judgement::SpinnerBonus => {
+            self.score_v1 += SCORE_SPINNER_BONUS as f64;
+            self.bonus += SCORE_SPINNER_BONUS as f64;
+         }
+         Judgement::SliderBreak | Judgement::SliderEndMiss => (),
+      }
+   }
+
+   pub fn mode(&self) -> ScoringMode {
+      self.mode
+   }
+
+   pub fn hit_counts(&self) -> &HitCounts {
+      &self.hit_counts
+   }
+
+   pub fn value(&self) -> u64 {
+      match self.mode {
+         ScoringMode::ScoreV1 => self.score_v1.round() as u64,
+         ScoringMode::ScoreV2 => {
+            let combo = SCORE_V2_COMBO_PORTION * self.fraction(self.combo_portion, self.max_combo_portion);
+            let accuracy = SCORE_V2_ACCURACY_PORTION
+               * accuracy(&self.hit_counts).powi(SCORE_V2_ACCURACY_EXPONENT)
+               * self.progress();
+            (SCORE_MAX * (combo + accuracy) * self.mod_multiplier + self.bonus).round() as u64
+         }
+         ScoringMode::ScoreOsru => {
+            (SCORE_MAX * accuracy(&self.hit_counts) * self.progress() * self.mod_multiplier).round() as u64
+         }
+      }
+   }
+
+   /// Fraction of the objects judged so far
+   fn progress(&self) -> f64 {
+      self.fraction(self.hit_counts.total() as f64, self.total_objects as f64)
+   }
+
+   fn fraction(&self, value: f64, max: f64) -> f64 {
+      if max > 0.0 {
+         value / max
+      } else {
+         0.0
+      }
+   }
+}
+impl Default for Score {
+   fn default() -> Self {
+      Score::new(ScoringMode::ScoreV1, &OsruGameModsActive::new(), 0.0, &[])
+   }
+}
+
+pub fn draw_score(canvas: &mut WindowCanvas, texture_manager: &TextureManager, score: &Score) {
+   let viewport = PixRect::new_from_sdl2_rect(canvas.viewport());
+   let position = Pix2D::new(viewport.width() * SCORE_POSITION_X, viewport.height() * SCORE_POSITION_Y);
+   draw_number(
+      canvas,
+      texture_manager,
+      score.value(),
+      TextureName::default_digit,
+      &position,
+      ScalingFactor(SCORE_SCALING),
+      Colour { r: u8::MAX, g: u8::MAX, b: u8::MAX, a: u8::MAX },
+   );
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn test_score() {
+      use HitSuccess::*;
+      use Judgement::*;
+      let play = [Object(Great), SliderTick, SliderTick, Slider(Great), Object(Good), Object(Great)];
+      let perfect = [Object(Great), SliderTick, SliderTick, Slider(Great), Object(Great), Object(Great)];
+      let mods = OsruGameModsActive::new();
+
+      println!("Testing ScoreV1");
+      let mut score = Score::new(ScoringMode::ScoreV1, &mods, 5.0, &perfect);
+      let mut combo = Combo::new(&perfect);
+      for judgement in play.iter() {
+         score.judge(judgement, &combo);
+         combo.judge(judgement);
+      }
+      // 300 + 10 + 10 + (300 + 300 * 2 * 5 / 25) + (100 + 100 * 2 * 5 / 25) + (300 + 300 * 3 * 5 / 25)
+      assert_eq!(1360, score.value());
+
+      println!("Testing ScoreV2");
+      let mut score = Score::new(ScoringMode::ScoreV2, &mods, 5.0, &perfect);
+      let mut combo = Combo::new(&perfect);
+      for judgement in perfect.iter() {
+         score.judge(judgement, &combo);
+         combo.judge(judgement);
+      }
+      assert_eq!(1_000_000, score.value());
+      score.judge(&SpinnerBonus, &combo);
+      assert_eq!(1_001_000, score.value());
+
+      println!("Testing ScoreOsru");
+      let mut score = Score::new(ScoringMode::ScoreOsru, &mods, 5.0, &perfect);
+      let mut combo = Combo::new(&perfect);
+      for judgement in play.iter() {
+         score.judge(judgement, &combo);
+         combo.judge(judgement);
+      }
+      assert_eq!(833_333, score.value());
+   }
+}
@@ -4,6 +4,7 @@
 pub enum SampleSetType{ NoCustom, normal, soft, drum}
 */
 pub mod pixel;
+pub mod playfield;
 
 use enum_iterator::IntoEnumIterator;
 use pixel::*;
@@ -20,6 +21,9 @@ pub const INTERPOLATE_MOUSE_POSITION: bool = false;
 
 pub const BEATMAP_TIMING_OFFSET: Duration = Duration::from_secs(2);
 
+pub const BACKGROUND_DIM: u8 = u8::MAX / 4 * 3;
+pub const BREAK_BACKGROUND_DIM: u8 = u8::MAX / 4;
+
 pub const LIMIT_FPS: bool = true;
 pub const TIME_PER_FRAME: Duration = Duration::from_nanos(999_999_999 / (144 * 3));
 
@@ -211,7 +215,7 @@ impl OsruType {
 //------------------
 
 pub fn parse_key_value<'a>(line: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
-   let split: Vec<&str> = line.trim().split(separator).collect();
+   let split: Vec<&str> = line.trim().splitn(2, separator).collect();
    if split.len() == 2 {
       let result = Some((split[0].trim(), split[1].trim()));
       return result;
@@ -268,22 +272,6 @@ pub fn calculate_texture_viewport(
    PixRect::new(new_pos_x, new_pos_y, image_size.x(), image_size.y())
 }
 
-pub fn osru_pos_to_screen_pos(osru_coord: &Pix2D, viewport_size: &PixRect) -> Pix2D {
-   let scaling_factor = scaling_factor(&DEFAULT_WINDOW_SIZE, viewport_size, Letterboxing::Allow);
-
-   let new_viewport_width = DEFAULT_WINDOW_SIZE.x() * scaling_factor;
-   let new_viewport_height = DEFAULT_WINDOW_SIZE.y() * scaling_factor;
-
-   let new_viewport_offset_x = (viewport_size.width() - new_viewport_width) / 2;
-   let new_viewport_offset_y = (viewport_size.height() - new_viewport_height) / 2;
-
-   let osru_coord = osru_coord.to_screen_pix();
-   let new_coord_x = osru_coord.x() * scaling_factor + new_viewport_offset_x;
-   let new_coord_y = osru_coord.y() * scaling_factor + new_viewport_offset_y;
-
-   Pix2D::new(new_coord_x, new_coord_y)
-}
-
 pub fn cursor_in_range(circle_pos: &Pix2D, cursor_pos: &Pix2D, radius: &Pix) -> bool {
    let diff = *circle_pos - *cursor_pos;
    let x_sq = diff.x().get().powi(2);
@@ -298,6 +286,7 @@ pub fn cursor_in_range(circle_pos: &Pix2D, cursor_pos: &Pix2D, radius: &Pix) ->
 
 pub fn display_background_image(
    canvas: &mut sdl2::render::WindowCanvas, texture: &mut sdl2::render::Texture, letterboxing: Letterboxing,
+   dim: u8,
 ) {
    let screen_viewport = PixRect::new_from_sdl2_rect(canvas.viewport());
 
@@ -318,15 +307,89 @@ pub fn display_background_image(
    canvas.clear();
    canvas.copy(&texture, None, Some(dst_viewport)).unwrap();
    canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
-   canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, u8::MAX / 4 * 3));
+   canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, dim));
    canvas.fill_rect(canvas.viewport()).unwrap();
 }
 
+/// Draws `number` centred on `screen_pos`, one sprite per digit
+pub fn draw_number(
+   canvas: &mut sdl2::render::WindowCanvas, texture_manager: &TextureManager, number: u64,
+   digit_texture: fn(u32) -> TextureName, screen_pos: &Pix2D, image_scaling: ScalingFactor,
+   colour: Colour<u8>,
+) {
+   let glyphs: Vec<TextureName> =
+      number.to_string().chars().map(|c| digit_texture(c.to_digit(10).unwrap())).collect();
+   draw_glyphs(canvas, texture_manager, &glyphs, screen_pos, image_scaling, colour);
+}
+
+/// Draws text made of the characters `TextureName::glyph` knows, skipping any others
+pub fn draw_text(
+   canvas: &mut sdl2::render::WindowCanvas, texture_manager: &TextureManager, text: &str, screen_pos: &Pix2D,
+   image_scaling: ScalingFactor, colour: Colour<u8>,
+) {
+   let glyphs: Vec<TextureName> = text.chars().filter_map(TextureName::glyph).collect();
+   draw_glyphs(canvas, texture_manager, &glyphs, screen_pos, image_scaling, colour);
+}
+
+/// Draws a whole texture centred on `screen_pos`
+pub fn draw_texture(
+   canvas: &mut sdl2::render::WindowCanvas, texture_manager: &TextureManager, name: TextureName,
+   screen_pos: &Pix2D, scaling: ScalingFactor,
+) {
+   let texture = texture_manager.get(name);
+   let texture = texture.borrow();
+   let image_size = Pix2D::new(
+      Pix::screen_pix(texture.query().width as f32),
+      Pix::screen_pix(texture.query().height as f32),
+   );
+   let viewport = calculate_texture_viewport(
+      screen_pos,
+      &image_size,
+      &PixRect::new_from_sdl2_rect(canvas.viewport()),
+      scaling,
+   );
+   canvas.copy(&texture, None, viewport.to_sdl2_rect()).unwrap();
+}
+
+/// Draws the textures side by side, centred on `screen_pos`
+fn draw_glyphs(
+   canvas: &mut sdl2::render::WindowCanvas, texture_manager: &TextureManager, glyphs: &[TextureName],
+   screen_pos: &Pix2D, image_scaling: ScalingFactor, colour: Colour<u8>,
+) {
+   let total_width = glyphs
+      .iter()
+      .fold(Pix::screen_pix(0.0), |width, glyph| width + texture_manager.size(*glyph).x() * image_scaling.0);
+
+   let mut digit_left = screen_pos.x() - total_width / 2;
+   for glyph in glyphs {
+      let texture = texture_manager.get(*glyph);
+      let mut texture = texture.borrow_mut();
+      texture.set_alpha_mod(colour.a);
+      texture.set_color_mod(colour.r, colour.g, colour.b);
+
+      let image_size = Pix2D::new(
+         Pix::screen_pix(texture.query().width as f32),
+         Pix::screen_pix(texture.query().height as f32),
+      );
+      let digit_width = image_size.x() * image_scaling.0;
+      let digit_centre = Pix2D::new(digit_left + digit_width / 2, screen_pos.y());
+      let viewport = calculate_texture_viewport(
+         &digit_centre,
+         &image_size,
+         &PixRect::new_from_sdl2_rect(canvas.viewport()),
+         image_scaling,
+      );
+      canvas.copy(&texture, None, viewport.to_sdl2_rect()).unwrap();
+      digit_left = digit_left + digit_width;
+   }
+}
+
 /////////////////////////////
 
 //use enum_iterator::IntoEnumIterator;
 use sdl2::image::LoadTexture;
-use sdl2::render::{Texture, TextureCreator};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{BlendMode, Texture, TextureCreator};
 use sdl2::video::WindowContext;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -338,6 +401,67 @@ pub enum TextureName {
    Background,
    ApproachCircle,
    HitCircle,
+   SliderBall,
+   SliderBodyCircle,
+   SliderBodyBuffer,
+   SliderFollowCircle,
+   SliderScorePoint,
+   ReverseArrow,
+   SpinnerCircle,
+   SpinnerApproachCircle,
+   FlashlightMask,
+   FlashlightDim,
+   ScoreDot,
+   ScorePercent,
+   Hit300,
+   Hit100,
+   Hit50,
+   Hit0,
+   RankingXH,
+   RankingX,
+   RankingSH,
+   RankingS,
+   RankingA,
+   RankingB,
+   RankingC,
+   RankingD,
+   Default0,
+   Default1,
+   Default2,
+   Default3,
+   Default4,
+   Default5,
+   Default6,
+   Default7,
+   Default8,
+   Default9,
+}
+impl TextureName {
+   pub fn default_digit(digit: u32) -> TextureName {
+      use TextureName::*;
+      match digit {
+         0 => Default0,
+         1 => Default1,
+         2 => Default2,
+         3 => Default3,
+         4 => Default4,
+         5 => Default5,
+         6 => Default6,
+         7 => Default7,
+         8 => Default8,
+         9 => Default9,
+         _ => panic!["{} is not a digit", digit],
+      }
+   }
+
+   /// Texture of a character in numbers such as the accuracy, if the skin has one
+   pub fn glyph(character: char) -> Option<TextureName> {
+      match character {
+         '.' => Some(TextureName::ScoreDot),
+         '%' => Some(TextureName::ScorePercent),
+         _ => character.to_digit(10).map(TextureName::default_digit),
+      }
+   }
 }
 
 pub struct TextureManager<'a> {
@@ -358,6 +482,61 @@ impl<'s> TextureManager<'s> {
       }
    }
 
+   /// Generates a white, anti-aliased filled circle to be tinted with `set_color_mod`
+   pub fn create_circle(&mut self, name: TextureName, radius: u32) {
+      let size = radius * 2;
+      let mut texture =
+         self.texture_creator.create_texture_static(PixelFormatEnum::RGBA32, size, size).unwrap();
+      let mut pixels = vec![u8::MAX; (size * size * 4) as usize];
+      for y in 0..size {
+         for x in 0..size {
+            let dx = x as f32 + 0.5 - radius as f32;
+            let dy = y as f32 + 0.5 - radius as f32;
+            let coverage = (radius as f32 - (dx * dx + dy * dy).sqrt()).clamp(0.0, 1.0);
+            pixels[((y * size + x) * 4 + 3) as usize] = (coverage * u8::MAX as f32) as u8;
+         }
+      }
+      texture.update(None, &pixels, (size * 4) as usize).unwrap();
+      texture.set_blend_mode(BlendMode::Blend);
+      self.textures.insert(name, Rc::new(RefCell::new(texture)));
+   }
+
+   /// Generates a black square with a transparent hole in the middle whose edge fades over `edge` pixels,
+   /// or if `inverted` a black disc that fades out over its edge
+   pub fn create_mask(&mut self, name: TextureName, radius: u32, edge: u32, inverted: bool) {
+      let size = radius * 2;
+      let mut texture =
+         self.texture_creator.create_texture_static(PixelFormatEnum::RGBA32, size, size).unwrap();
+      let mut pixels = vec![0; (size * size * 4) as usize];
+      for y in 0..size {
+         for x in 0..size {
+            let dx = x as f32 + 0.5 - radius as f32;
+            let dy = y as f32 + 0.5 - radius as f32;
+            let distance = (dx * dx + dy * dy).sqrt();
+            let coverage = ((distance - (radius - edge) as f32) / edge as f32).clamp(0.0, 1.0);
+            let coverage = if inverted { 1.0 - coverage } else { coverage };
+            pixels[((y * size + x) * 4 + 3) as usize] = (coverage * u8::MAX as f32) as u8;
+         }
+      }
+      texture.update(None, &pixels, (size * 4) as usize).unwrap();
+      texture.set_blend_mode(BlendMode::Blend);
+      self.textures.insert(name, Rc::new(RefCell::new(texture)));
+   }
+
+   /// Creates a transparent texture that can be drawn into with `WindowCanvas::with_texture_canvas`
+   pub fn create_render_target(&mut self, name: TextureName, size: &PixRect) {
+      let mut texture = self
+         .texture_creator
+         .create_texture_target(
+            PixelFormatEnum::RGBA32,
+            size.width().get_round() as u32,
+            size.height().get_round() as u32,
+         )
+         .unwrap();
+      texture.set_blend_mode(BlendMode::Blend);
+      self.textures.insert(name, Rc::new(RefCell::new(texture)));
+   }
+
    pub fn unload_all(&mut self) {
       self.textures.clear();
    }
@@ -0,0 +1,127 @@
+use super::pixel::*;
+use super::*;
+
+pub const PLAYFIELD_WIDTH: f32 = 512.0;
+pub const PLAYFIELD_HEIGHT: f32 = 384.0;
+// the playfield sits slightly below the centre of the 640x480 screen
+pub const PLAYFIELD_OFFSET_Y: f32 = 8.0;
+
+/// Moves object positions around the playfield, in osu!pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlayfieldTransform {
+   FlipVertical,
+   MirrorHorizontal,
+   /// Clockwise rotation in radians around the playfield's centre
+   Rotate(f32),
+}
+impl PlayfieldTransform {
+   pub fn apply(&self, position: &Pix2D) -> Pix2D {
+      use PlayfieldTransform::*;
+      let (x, y) = (position.x().get(), position.y().get());
+      let (x, y) = match self {
+         FlipVertical => (x, PLAYFIELD_HEIGHT - y),
+         MirrorHorizontal => (PLAYFIELD_WIDTH - x, y),
+         Rotate(angle) => {
+            let (dx, dy) = (x - PLAYFIELD_WIDTH / 2.0, y - PLAYFIELD_HEIGHT / 2.0);
+            let (sin, cos) = angle.sin_cos();
+            (PLAYFIELD_WIDTH / 2.0 + dx * cos - dy * sin, PLAYFIELD_HEIGHT / 2.0 + dx * sin + dy * cos)
+         }
+      };
+      Pix2D::new(Pix::osru_pix(x), Pix::osru_pix(y))
+   }
+
+   pub fn inverse(&self) -> PlayfieldTransform {
+      use PlayfieldTransform::*;
+      match self {
+         Rotate(angle) => Rotate(-angle),
+         transform => *transform,
+      }
+   }
+}
+
+/// Maps osu!pixels on the 512x384 playfield to screen pixels and back
+#[derive(Debug, Copy, Clone)]
+pub struct Playfield {
+   scaling_factor: f32,
+   origin: Pix2D,
+}
+impl Playfield {
+   pub fn new(viewport_size: &PixRect) -> Playfield {
+      let scaling_factor = scaling_factor(&DEFAULT_WINDOW_SIZE, viewport_size, Letterboxing::Allow);
+      let width = PLAYFIELD_WIDTH * scaling_factor;
+      let height = PLAYFIELD_HEIGHT * scaling_factor;
+      let origin = Pix2D::new(
+         viewport_size.x() + (viewport_size.width() - Pix::screen_pix(width)) / 2,
+         viewport_size.y()
+            + (viewport_size.height() - Pix::screen_pix(height)) / 2
+            + Pix::screen_pix(PLAYFIELD_OFFSET_Y * scaling_factor),
+      );
+      Playfield { scaling_factor, origin }
+   }
+
+   /// Screen pixels per osu!pixel
+   pub fn scaling_factor(&self) -> f32 {
+      self.scaling_factor
+   }
+
+   pub fn screen_position(&self, osru_pos: &Pix2D) -> Pix2D {
+      let osru_pos = osru_pos.to_screen_pix();
+      Pix2D::new(
+         osru_pos.x() * self.scaling_factor + self.origin.x(),
+         osru_pos.y() * self.scaling_factor + self.origin.y(),
+      )
+   }
+
+   /// Inverse of `screen_position`, e.g. for the cursor position
+   pub fn osru_position(&self, screen_pos: &Pix2D) -> Pix2D {
+      let x = (screen_pos.x().get() - self.origin.x().get()) / self.scaling_factor;
+      let y = (screen_pos.y().get() - self.origin.y().get()) / self.scaling_factor;
+      Pix2D::new(Pix::osru_pix(x), Pix::osru_pix(y))
+   }
+
+   pub fn screen_length(&self, osru_length: &Pix) -> Pix {
+      osru_length.to_screen_pix() * self.scaling_factor
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use assert_approx_eq::assert_approx_eq;
+
+   #[test]
+   fn test_playfield() {
+      let playfield = Playfield::new(&PixRect::new_from_sdl2_rect(sdl2::rect::Rect::new(0, 0, 640, 480)));
+      let top_left = playfield.screen_position(&Pix2D::new(Pix::osru_pix(0.0), Pix::osru_pix(0.0)));
+      assert_approx_eq!(64.0, top_left.x().get());
+      assert_approx_eq!(56.0, top_left.y().get());
+
+      println!("Testing scaled and letterboxed viewport");
+      let playfield = Playfield::new(&PixRect::new_from_sdl2_rect(sdl2::rect::Rect::new(0, 0, 1920, 1080)));
+      assert_approx_eq!(2.25, playfield.scaling_factor());
+      let centre = playfield.screen_position(&Pix2D::new(Pix::osru_pix(256.0), Pix::osru_pix(192.0)));
+      assert_approx_eq!(960.0, centre.x().get());
+      assert_approx_eq!(558.0, centre.y().get());
+      assert_approx_eq!(72.0, playfield.screen_length(&Pix::osru_pix(32.0)).get());
+
+      println!("Testing inverse");
+      let cursor = playfield.osru_position(&centre);
+      assert_approx_eq!(256.0, cursor.x().get(), 1e-3);
+      assert_approx_eq!(192.0, cursor.y().get(), 1e-3);
+   }
+
+   #[test]
+   fn test_transforms() {
+      use PlayfieldTransform::*;
+      let position = Pix2D::new(Pix::osru_pix(100.0), Pix::osru_pix(50.0));
+      assert_approx_eq!(334.0, FlipVertical.apply(&position).y().get());
+      assert_approx_eq!(412.0, MirrorHorizontal.apply(&position).x().get());
+
+      let rotated = Rotate(std::f32::consts::FRAC_PI_2).apply(&position);
+      assert_approx_eq!(398.0, rotated.x().get(), 1e-3);
+      assert_approx_eq!(36.0, rotated.y().get(), 1e-3);
+      let restored = Rotate(std::f32::consts::FRAC_PI_2).inverse().apply(&rotated);
+      assert_approx_eq!(100.0, restored.x().get(), 1e-3);
+      assert_approx_eq!(50.0, restored.y().get(), 1e-3);
+   }
+}
@@ -51,6 +51,10 @@ impl InputManager {
       self.reference_time.as_mut().unwrap().start();
    }
 
+   pub fn set_playback_rate(&mut self, playback_rate: f64) {
+      self.reference_time.as_mut().unwrap().set_playback_rate(playback_rate);
+   }
+
    pub fn set_reference_time(&mut self) {
       '_clear_events: for ev in self.event_pump.poll_iter() {
          match ev {
@@ -25,5 +25,11 @@ use std::{
 };
 
 fn main() {
-   let g = game::Game::start();
+   let mut mods = game::gamemod::OsruGameModsActive::new();
+   for arg in std::env::args().skip(1) {
+      for unknown in mods.enable_from_acronyms(&arg) {
+         println!("Unknown mod: {}", unknown);
+      }
+   }
+   let g = game::Game::start(mods);
 }
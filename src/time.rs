@@ -35,11 +35,21 @@ pub struct ReferenceTime {
    ref_time: Instant,
    ref_sdl_time: SdlTime,
    start_time: Duration,
+   playback_rate: f64,
 }
 impl ReferenceTime {
    pub fn new(ref_time: Instant, ref_sdl_time: SdlTime) -> ReferenceTime {
-      ReferenceTime { ref_time, start_time: Duration::from_secs(0), ref_sdl_time }
+      ReferenceTime { ref_time, start_time: Duration::from_secs(0), ref_sdl_time, playback_rate: 1.0 }
    }
+
+   /// Gameplay time passes `playback_rate` times as fast as wall-clock time
+   pub fn set_playback_rate(&mut self, playback_rate: f64) {
+      self.playback_rate = playback_rate;
+   }
+   pub fn playback_rate(&self) -> f64 {
+      self.playback_rate
+   }
+
    pub fn start(&mut self) {
       self.start_time = self.ref_time.elapsed();
    }
@@ -50,14 +60,14 @@ impl ReferenceTime {
          result = 1000
       }
       result -= 1000;
-      Duration::from_millis(result)
+      Duration::from_millis(result).mul_f64(self.playback_rate)
    }
 
    pub fn elapsed_sys_time(&self, current: Instant) -> Duration {
-      current.duration_since(self.ref_time) - self.start_time
+      (current.duration_since(self.ref_time) - self.start_time).mul_f64(self.playback_rate)
    }
 
    pub fn elapsed_now(&self) -> Duration {
-      self.ref_time.elapsed() - self.start_time
+      (self.ref_time.elapsed() - self.start_time).mul_f64(self.playback_rate)
    }
 }